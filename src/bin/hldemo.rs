@@ -0,0 +1,535 @@
+//! A command-line tool for inspecting Goldsource demos.
+
+extern crate clap;
+#[macro_use]
+extern crate error_chain;
+extern crate hldemo;
+extern crate serde_json;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use hldemo::{lossy_string, Demo, Frame, FrameData, NetMsgFrameType};
+
+mod errors {
+    error_chain! {
+        foreign_links {
+            Demo(::hldemo::errors::Error);
+            Io(::std::io::Error);
+            Json(::serde_json::Error);
+        }
+    }
+}
+use errors::*;
+
+quick_main!(run);
+
+/// Frame types accepted by `--type`.
+const FRAME_TYPES: &[&str] = &["NetMsg",
+                               "DemoStart",
+                               "ConsoleCommand",
+                               "ClientData",
+                               "NextSection",
+                               "Event",
+                               "WeaponAnim",
+                               "Sound",
+                               "DemoBuffer"];
+
+/// Frame filters common to the subcommands which output frames.
+struct Filter {
+    entry: Option<usize>,
+    types: Option<Vec<String>>,
+    from: Option<f32>,
+    to: Option<f32>,
+}
+
+impl Filter {
+    fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let entry = match matches.value_of("entry") {
+            Some(entry) => Some(entry.parse().chain_err(|| "invalid entry index")?),
+            None => None,
+        };
+        let types = matches.values_of("type")
+                           .map(|types| types.map(str::to_lowercase).collect());
+
+        Ok(Filter { entry,
+                    types,
+                    from: time(matches, "from")?,
+                    to: time(matches, "to")? })
+    }
+
+    fn matches(&self, entry: usize, frame: &Frame) -> bool {
+        if self.entry.map_or(false, |e| e != entry) {
+            return false;
+        }
+
+        if let Some(ref types) = self.types {
+            let frame_type = frame_type(&frame.data).to_lowercase();
+            if !types.contains(&frame_type) {
+                return false;
+            }
+        }
+
+        self.from.map_or(true, |from| frame.time >= from)
+        && self.to.map_or(true, |to| frame.time <= to)
+    }
+
+    /// Removes the frames which don't match the filter.
+    fn apply(&self, demo: &mut Demo) {
+        for (i, entry) in demo.directory.entries.iter_mut().enumerate() {
+            entry.frames.retain(|frame| self.matches(i, frame));
+        }
+    }
+}
+
+fn time(matches: &ArgMatches, name: &str) -> Result<Option<f32>> {
+    match matches.value_of(name) {
+        Some(value) => {
+            let time = value.parse()
+                            .chain_err(|| format!("invalid time for --{}: {}", name, value))?;
+            Ok(Some(time))
+        }
+        None => Ok(None),
+    }
+}
+
+fn frame_number(matches: &ArgMatches, name: &str) -> Result<Option<i32>> {
+    match matches.value_of(name) {
+        Some(value) => {
+            let frame = value.parse()
+                             .chain_err(|| format!("invalid frame for --{}: {}", name, value))?;
+            Ok(Some(frame))
+        }
+        None => Ok(None),
+    }
+}
+
+fn filter_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![Arg::with_name("entry").long("entry")
+                                .value_name("INDEX")
+                                .help("Only output frames of the given directory entry"),
+         Arg::with_name("type").long("type")
+                               .value_name("TYPE")
+                               .multiple(true)
+                               .use_delimiter(true)
+                               .number_of_values(1)
+                               .possible_values(FRAME_TYPES)
+                               .case_insensitive(true)
+                               .help("Only output frames of the given types"),
+         Arg::with_name("from").long("from")
+                               .value_name("SECONDS")
+                               .help("Only output frames at or after the given time"),
+         Arg::with_name("to").long("to")
+                             .value_name("SECONDS")
+                             .help("Only output frames at or before the given time")]
+}
+
+fn trim_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![Arg::with_name("from").long("from")
+                               .value_name("SECONDS")
+                               .required_unless("start-frame")
+                               .conflicts_with_all(&["start-frame", "end-frame"])
+                               .help("The start time of the range"),
+         Arg::with_name("to").long("to")
+                             .value_name("SECONDS")
+                             .requires("from")
+                             .help("The end time of the range"),
+         Arg::with_name("start-frame").long("start-frame")
+                                      .value_name("FRAME")
+                                      .help("The first frame number of the range"),
+         Arg::with_name("end-frame").long("end-frame")
+                                    .value_name("FRAME")
+                                    .requires("start-frame")
+                                    .help("The last frame number of the range")]
+}
+
+fn file_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("FILE").required(true)
+                          .help("The demo file")
+}
+
+fn run() -> Result<()> {
+    let matches =
+        App::new("hldemo").version(env!("CARGO_PKG_VERSION"))
+                          .about("Inspects Goldsource demos.")
+                          .setting(AppSettings::SubcommandRequiredElseHelp)
+                          .subcommand(SubCommand::with_name("info")
+                                          .about("Prints the header and the directory")
+                                          .arg(file_arg()))
+                          .subcommand(SubCommand::with_name("probe")
+                                          .about("Prints what can be read from a file which may \
+                                                  not be a valid demo")
+                                          .arg(file_arg()))
+                          .subcommand(SubCommand::with_name("frames")
+                                          .about("Prints the frames")
+                                          .arg(file_arg())
+                                          .args(&filter_args()))
+                          .subcommand(SubCommand::with_name("export")
+                                          .about("Exports the demo as JSON, the frames as CSV or \
+                                                  the inputs as a TAS script")
+                                          .arg(file_arg())
+                                          .arg(Arg::with_name("format")
+                                                   .long("format")
+                                                   .value_name("FORMAT")
+                                                   .possible_values(&["json", "csv", "hltas"])
+                                                   .default_value("json")
+                                                   .help("The output format"))
+                                          .arg(Arg::with_name("output")
+                                                   .long("output")
+                                                   .short("o")
+                                                   .value_name("FILE")
+                                                   .help("The output file, standard output by \
+                                                          default"))
+                                          .args(&filter_args()))
+                          .subcommand(SubCommand::with_name("trim")
+                                          .about("Cuts a range of the playback into a new demo")
+                                          .arg(file_arg())
+                                          .arg(Arg::with_name("OUTPUT").required(true)
+                                                                       .help("The output demo"))
+                                          .args(&trim_args()))
+                          .subcommand(SubCommand::with_name("concat")
+                                          .about("Merges several demos into one")
+                                          .arg(Arg::with_name("FILES").required(true)
+                                                                      .multiple(true)
+                                                                      .help("The demo files"))
+                                          .arg(Arg::with_name("output")
+                                                   .long("output")
+                                                   .short("o")
+                                                   .value_name("FILE")
+                                                   .required(true)
+                                                   .help("The output demo"))
+                                          .arg(Arg::with_name("force")
+                                                   .long("force")
+                                                   .help("Merge demos with different net \
+                                                          protocols or game directories")))
+                          .subcommand(SubCommand::with_name("validate")
+                                          .about("Checks the demo for errors, failing if there \
+                                                  are any errors or warnings")
+                                          .arg(file_arg()))
+                          .get_matches();
+
+    match matches.subcommand() {
+        ("info", Some(matches)) => info(matches),
+        ("probe", Some(matches)) => probe(matches),
+        ("frames", Some(matches)) => frames(matches),
+        ("export", Some(matches)) => export(matches),
+        ("trim", Some(matches)) => trim(matches),
+        ("concat", Some(matches)) => concat(matches),
+        ("validate", Some(matches)) => validate(matches),
+        _ => unreachable!(),
+    }
+}
+
+fn read(matches: &ArgMatches) -> Result<Vec<u8>> {
+    let filename = matches.value_of("FILE").unwrap();
+    fs::read(filename).chain_err(|| format!("couldn't read {}", filename))
+}
+
+fn info(matches: &ArgMatches) -> Result<()> {
+    let bytes = read(matches)?;
+    let demo = Demo::parse_without_frames(&bytes).chain_err(|| "couldn't parse the demo")?;
+
+    println!("Demo protocol: {}", demo.header.demo_protocol);
+    println!("Net protocol: {}", demo.header.net_protocol);
+    println!("Map name: {}", lossy_string(&demo.header.map_name));
+    println!("Game directory: {}", lossy_string(&demo.header.game_dir));
+    println!("Map CRC: {}", demo.header.map_crc);
+    println!("Directory offset: {}", demo.header.directory_offset);
+
+    for (i, entry) in demo.directory.entries.iter().enumerate() {
+        println!();
+        println!("Entry {}:", i);
+        println!("\tType: {}", entry.entry_type);
+        println!("\tDescription: {}", lossy_string(&entry.description));
+        println!("\tFlags: {}", entry.flags);
+        println!("\tCD track: {}", entry.cd_track);
+        println!("\tTrack time: {}", entry.track_time);
+        println!("\tFrame count: {}", entry.frame_count);
+        println!("\tOffset: {}", entry.offset);
+        println!("\tFile length: {}", entry.file_length);
+    }
+
+    Ok(())
+}
+
+fn probe(matches: &ArgMatches) -> Result<()> {
+    let bytes = read(matches)?;
+    let probe = hldemo::probe(&bytes);
+
+    let unknown = || "unknown".to_string();
+    let number = |x: Option<i32>| x.map(|x| x.to_string()).unwrap_or_else(unknown);
+
+    println!("Magic: {:?}", String::from_utf8_lossy(&probe.magic));
+    println!("Dialect: {:?}", probe.dialect);
+    println!("Demo protocol: {}", number(probe.demo_protocol));
+    println!("Net protocol: {}", number(probe.net_protocol));
+    println!("Map name: {}", probe.map_name.unwrap_or_else(unknown));
+    println!("Game directory: {}", probe.game_dir.unwrap_or_else(unknown));
+    println!("Playback duration: {}",
+             probe.playback_duration.map(|x| x.to_string()).unwrap_or_else(unknown));
+    println!("Truncated: {}", if probe.truncated { "yes" } else { "no" });
+
+    Ok(())
+}
+
+fn frames(matches: &ArgMatches) -> Result<()> {
+    let bytes = read(matches)?;
+    let filter = Filter::from_matches(matches)?;
+    let mut demo = Demo::parse(&bytes).chain_err(|| "couldn't parse the demo")?;
+    filter.apply(&mut demo);
+
+    let stdout = io::stdout();
+    let mut w = BufWriter::new(stdout.lock());
+
+    for (i, entry) in demo.directory.entries.iter().enumerate() {
+        if filter.entry.map_or(false, |e| e != i) {
+            continue;
+        }
+
+        writeln!(w, "Entry {}:", i)?;
+
+        for frame in &entry.frames {
+            writeln!(w,
+                     "\tf={} t={} type={}{}",
+                     frame.frame,
+                     frame.time,
+                     frame_type_string(&frame.data),
+                     frame_extra_info(&frame.data))?;
+        }
+
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+fn export(matches: &ArgMatches) -> Result<()> {
+    let bytes = read(matches)?;
+    let filter = Filter::from_matches(matches)?;
+    let mut demo = Demo::parse(&bytes).chain_err(|| "couldn't parse the demo")?;
+    filter.apply(&mut demo);
+
+    let stdout = io::stdout();
+    let output: Box<dyn Write> = match matches.value_of("output") {
+        Some(filename) => {
+            let file = File::create(filename).chain_err(|| {
+                                                  format!("couldn't create {}", filename)
+                                              })?;
+            Box::new(file)
+        }
+        None => Box::new(stdout.lock()),
+    };
+    let mut w = BufWriter::new(output);
+
+    match matches.value_of("format").unwrap() {
+        "json" => {
+            serde_json::to_writer_pretty(&mut w, &demo)?;
+            writeln!(w)?;
+        }
+        "csv" => write_csv(&mut w, &demo)?,
+        "hltas" => hldemo::hltas::write(&demo, &mut w)?,
+        _ => unreachable!(),
+    }
+
+    w.flush()?;
+    Ok(())
+}
+
+fn trim(matches: &ArgMatches) -> Result<()> {
+    let bytes = read(matches)?;
+    let demo = Demo::parse(&bytes).chain_err(|| "couldn't parse the demo")?;
+
+    let clip = if matches.is_present("from") {
+        let from = time(matches, "from")?.unwrap();
+        let to = time(matches, "to")?.unwrap_or(f32::INFINITY);
+        demo.trim_time(from, to)?
+    } else {
+        let start = frame_number(matches, "start-frame")?.unwrap();
+        let end = frame_number(matches, "end-frame")?.unwrap_or(i32::MAX);
+        demo.trim_frames(start, end)?
+    };
+
+    let filename = matches.value_of("OUTPUT").unwrap();
+    let file = File::create(filename).chain_err(|| format!("couldn't create {}", filename))?;
+    clip.write(BufWriter::new(file))?;
+    Ok(())
+}
+
+fn concat(matches: &ArgMatches) -> Result<()> {
+    let filenames: Vec<_> = matches.values_of("FILES").unwrap().collect();
+
+    let mut files = Vec::with_capacity(filenames.len());
+    for filename in &filenames {
+        files.push(fs::read(filename).chain_err(|| format!("couldn't read {}", filename))?);
+    }
+
+    let mut demo: Option<Demo> = None;
+    for (filename, bytes) in filenames.iter().zip(&files) {
+        let other = Demo::parse(bytes).chain_err(|| format!("couldn't parse {}", filename))?;
+
+        demo = Some(match demo {
+                        Some(mut demo) => {
+                            if let Err(err) = demo.check_compatible(&other) {
+                                if !matches.is_present("force") {
+                                    return Err(err).chain_err(|| {
+                                                       format!("couldn't merge {}", filename)
+                                                   });
+                                }
+
+                                eprintln!("warning: {}: {}", filename, err);
+                            }
+
+                            demo.append(other);
+                            demo
+                        }
+                        None => other,
+                    });
+    }
+
+    let filename = matches.value_of("output").unwrap();
+    let file = File::create(filename).chain_err(|| format!("couldn't create {}", filename))?;
+    demo.unwrap().write(BufWriter::new(file))?;
+    Ok(())
+}
+
+fn validate(matches: &ArgMatches) -> Result<()> {
+    let bytes = read(matches)?;
+
+    let demo = match Demo::parse(&bytes) {
+        Ok(demo) => demo,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            for cause in err.iter().skip(1) {
+                eprintln!("\tcaused by: {}", cause);
+            }
+
+            if let Ok((_, diagnostics)) = Demo::parse_lenient(&bytes) {
+                for diagnostic in diagnostics {
+                    eprintln!("{}", diagnostic);
+                }
+            }
+
+            bail!("the demo is invalid");
+        }
+    };
+
+    // The directory is valid, but it may not match the frames.
+    let mut rebuilt = demo.directory.clone();
+    rebuilt.rebuild();
+
+    let mut warnings = 0;
+    for (i, (entry, expected)) in demo.directory
+                                      .entries
+                                      .iter()
+                                      .zip(&rebuilt.entries)
+                                      .enumerate()
+    {
+        if entry.frames.last().map(|f| &f.data) != Some(&FrameData::NextSection) {
+            eprintln!("warning: entry {} doesn't end with a NextSection frame", i);
+            warnings += 1;
+        }
+
+        if entry.frame_count != expected.frame_count {
+            eprintln!("warning: entry {} frame count is {}, but the frames span {}",
+                      i,
+                      entry.frame_count,
+                      expected.frame_count);
+            warnings += 1;
+        }
+
+        if entry.track_time != expected.track_time {
+            eprintln!("warning: entry {} track time is {}, but the frames span {}",
+                      i,
+                      entry.track_time,
+                      expected.track_time);
+            warnings += 1;
+        }
+    }
+
+    if warnings > 0 {
+        bail!("the demo has {} warnings", warnings);
+    }
+
+    println!("OK");
+    Ok(())
+}
+
+fn write_csv<W: Write>(w: &mut W, demo: &Demo) -> Result<()> {
+    writeln!(w,
+             "entry,frame,time,type,origin_x,origin_y,origin_z,pitch,yaw,roll,frametime,details")?;
+
+    for (i, entry) in demo.directory.entries.iter().enumerate() {
+        for frame in &entry.frames {
+            let (origin, viewangles, frametime) = match frame.data {
+                FrameData::NetMsg((_, ref data)) => {
+                    let params = &data.info.ref_params;
+                    (Some(params.simorg), Some(params.viewangles), Some(params.frametime))
+                }
+                FrameData::ClientData(ref data) => (Some(data.origin), Some(data.viewangles), None),
+                _ => (None, None, None),
+            };
+
+            let vector = |v: Option<[f32; 3]>| match v {
+                Some(v) => format!("{},{},{}", v[0], v[1], v[2]),
+                None => ",,".to_string(),
+            };
+
+            writeln!(w,
+                     "{},{},{},{},{},{},{},{}",
+                     i,
+                     frame.frame,
+                     frame.time,
+                     frame_type_string(&frame.data),
+                     vector(origin),
+                     vector(viewangles),
+                     frametime.map(|x| x.to_string()).unwrap_or_default(),
+                     csv_field(frame_extra_info(&frame.data).trim()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Quotes the field if it contains special characters.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Returns the frame type as accepted by `--type`.
+fn frame_type(data: &FrameData) -> &'static str {
+    match *data {
+        FrameData::NetMsg(_) => "NetMsg",
+        _ => frame_type_string(data),
+    }
+}
+
+fn frame_type_string(data: &FrameData) -> &'static str {
+    match *data {
+        FrameData::NetMsg((NetMsgFrameType::Start, _)) => "NetMsg Start",
+        FrameData::NetMsg((NetMsgFrameType::Normal, _)) => "NetMsg",
+        FrameData::NetMsg((NetMsgFrameType::Unknown(_), _)) => "NetMsg Unknown",
+        FrameData::DemoStart => "DemoStart",
+        FrameData::ConsoleCommand(_) => "ConsoleCommand",
+        FrameData::ClientData(_) => "ClientData",
+        FrameData::NextSection => "NextSection",
+        FrameData::Event(_) => "Event",
+        FrameData::WeaponAnim(_) => "WeaponAnim",
+        FrameData::Sound(_) => "Sound",
+        FrameData::DemoBuffer(_) => "DemoBuffer",
+    }
+}
+
+fn frame_extra_info(data: &FrameData) -> String {
+    match *data {
+        FrameData::ConsoleCommand(ref d) => format!(" command=`{}`", lossy_string(&d.command)),
+        FrameData::Sound(ref d) => format!(" sample=`{}`", lossy_string(&d.sample)),
+        FrameData::DemoBuffer(ref d) => format!(" size={}", d.buffer.len()),
+        FrameData::NetMsg((_, ref d)) => format!(" size={}", d.msg.len()),
+        _ => "".to_string(),
+    }
+}
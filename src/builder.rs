@@ -0,0 +1,201 @@
+//! Creating demos from frames.
+
+use std::borrow::Cow;
+use std::io::Write;
+
+use errors::*;
+use parse::directory::{ENTRY_TYPE_LOADING, ENTRY_TYPE_PLAYBACK};
+use parse::header::SUPPORTED_DEMO_PROTOCOL;
+use types::*;
+
+/// The network protocol of the current Half-Life versions.
+pub const DEFAULT_NET_PROTOCOL: i32 = 48;
+
+/// Builds a demo frame by frame.
+///
+/// Frames are added to the last started directory entry with the current time and frame number,
+/// which are set with `at()` and `advance()`. When the demo is built, every entry is terminated
+/// with a `NextSection` frame and the directory is rebuilt with `Demo::rebuild_directory()`, so
+/// the demo can be written as a valid demo file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::error::Error;
+/// #
+/// # fn try_main() -> Result<(), Box<Error>> {
+/// extern crate hldemo;
+///
+/// use std::fs::File;
+/// use std::io::BufWriter;
+///
+/// use hldemo::{DemoBuilder, NetMsgData, NetMsgFrameType};
+///
+/// let mut builder = DemoBuilder::new(&b"c1a0"[..], &b"valve"[..]);
+/// builder.loading().playback().demo_start();
+///
+/// for _ in 0..100 {
+///     builder.advance(0.01)
+///            .netmsg(NetMsgFrameType::Normal, NetMsgData::default());
+/// }
+///
+/// builder.write(BufWriter::new(File::create("synthetic.dem")?))?;
+/// #
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemoBuilder<'a> {
+    header: Header<'a>,
+    entries: Vec<DirectoryEntry<'a>>,
+    time: f32,
+    frame: i32,
+}
+
+impl<'a> DemoBuilder<'a> {
+    /// Creates a builder for a demo on the given map and game directory, without any directory
+    /// entries.
+    pub fn new<M, G>(map_name: M, game_dir: G) -> Self
+        where M: Into<Cow<'a, [u8]>>,
+              G: Into<Cow<'a, [u8]>>
+    {
+        DemoBuilder { header: Header { demo_protocol: SUPPORTED_DEMO_PROTOCOL,
+                                       net_protocol: DEFAULT_NET_PROTOCOL,
+                                       map_name: map_name.into(),
+                                       game_dir: game_dir.into(),
+                                       map_crc: 0,
+                                       directory_offset: 0 },
+                      entries: Vec::new(),
+                      time: 0f32,
+                      frame: 0 }
+    }
+
+    /// Sets the network protocol, `DEFAULT_NET_PROTOCOL` by default.
+    pub fn net_protocol(&mut self, net_protocol: i32) -> &mut Self {
+        self.header.net_protocol = net_protocol;
+        self
+    }
+
+    /// Sets the CRC of the map, zero by default.
+    pub fn map_crc(&mut self, map_crc: u32) -> &mut Self {
+        self.header.map_crc = map_crc;
+        self
+    }
+
+    /// Starts a new directory entry of the given type.
+    pub fn entry(&mut self, entry_type: i32) -> &mut Self {
+        self.entries.push(DirectoryEntry::from_frames(entry_type, Vec::new()));
+        self
+    }
+
+    /// Starts the loading segment.
+    #[inline]
+    pub fn loading(&mut self) -> &mut Self {
+        self.entry(ENTRY_TYPE_LOADING)
+    }
+
+    /// Starts a playback segment.
+    #[inline]
+    pub fn playback(&mut self) -> &mut Self {
+        self.entry(ENTRY_TYPE_PLAYBACK)
+    }
+
+    /// Sets the time and the frame number of the following frames.
+    pub fn at(&mut self, time: f32, frame: i32) -> &mut Self {
+        self.time = time;
+        self.frame = frame;
+        self
+    }
+
+    /// Moves on to the next engine frame, which comes `frametime` seconds later.
+    pub fn advance(&mut self, frametime: f32) -> &mut Self {
+        self.time += frametime;
+        self.frame += 1;
+        self
+    }
+
+    /// Adds a frame to the last entry.
+    ///
+    /// A playback segment is started if there are no entries yet.
+    pub fn frame(&mut self, data: FrameData<'a>) -> &mut Self {
+        if self.entries.is_empty() {
+            self.playback();
+        }
+
+        let frame = Frame { time: self.time,
+                            frame: self.frame,
+                            data };
+        self.entries.last_mut().unwrap().frames.push(frame);
+        self
+    }
+
+    /// Adds a NetMsg frame.
+    #[inline]
+    pub fn netmsg(&mut self, frame_type: NetMsgFrameType, data: NetMsgData<'a>) -> &mut Self {
+        self.frame(FrameData::NetMsg((frame_type, data)))
+    }
+
+    /// Adds a DemoStart frame.
+    #[inline]
+    pub fn demo_start(&mut self) -> &mut Self {
+        self.frame(FrameData::DemoStart)
+    }
+
+    /// Adds a ConsoleCommand frame.
+    ///
+    /// The command is padded with null bytes when the demo is written.
+    #[inline]
+    pub fn console_command<C: Into<Cow<'a, [u8]>>>(&mut self, command: C) -> &mut Self {
+        self.frame(FrameData::ConsoleCommand(ConsoleCommandData { command: command.into() }))
+    }
+
+    /// Adds a ClientData frame.
+    #[inline]
+    pub fn client_data(&mut self, data: ClientDataData) -> &mut Self {
+        self.frame(FrameData::ClientData(data))
+    }
+
+    /// Adds an Event frame.
+    #[inline]
+    pub fn event(&mut self, data: EventData) -> &mut Self {
+        self.frame(FrameData::Event(data))
+    }
+
+    /// Adds a WeaponAnim frame.
+    #[inline]
+    pub fn weapon_anim(&mut self, data: WeaponAnimData) -> &mut Self {
+        self.frame(FrameData::WeaponAnim(data))
+    }
+
+    /// Adds a Sound frame.
+    #[inline]
+    pub fn sound(&mut self, data: SoundData<'a>) -> &mut Self {
+        self.frame(FrameData::Sound(data))
+    }
+
+    /// Adds a DemoBuffer frame.
+    #[inline]
+    pub fn demo_buffer<B: Into<Cow<'a, [u8]>>>(&mut self, buffer: B) -> &mut Self {
+        self.frame(FrameData::DemoBuffer(DemoBufferData { buffer: buffer.into() }))
+    }
+
+    /// Builds the demo.
+    pub fn build(&self) -> Demo<'a> {
+        let mut demo = Demo { header: self.header.clone(),
+                              directory: Directory { entries: self.entries.clone() } };
+        demo.rebuild_directory();
+        demo
+    }
+
+    /// Builds the demo and writes it.
+    ///
+    /// Fails if there are no directory entries or if a frame doesn't fit into the demo format,
+    /// for example if a NetMsg is longer than `MAX_MESSAGE_LENGTH`.
+    pub fn write<W: Write>(&self, output: W) -> Result<()> {
+        self.build().write(output)
+    }
+}
@@ -0,0 +1,34 @@
+//! Byte string fields.
+//!
+//! Fixed-size byte strings in the demo are padded with null bytes. With the `serde` feature,
+//! byte strings are serialized up to the first null byte, with invalid UTF-8 replaced, and
+//! deserialized as owned bytes without the padding, which is added back when writing.
+
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Returns the byte string up to the first null byte.
+#[inline]
+pub fn trim_nul(bytes: &[u8]) -> &[u8] {
+    bytes.split(|&x| x == 0).next().unwrap()
+}
+
+/// Returns the byte string up to the first null byte as a string, with invalid UTF-8 replaced.
+#[inline]
+pub fn lossy_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(trim_nul(bytes)).into_owned()
+}
+
+#[cfg(feature = "serde")]
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&String::from_utf8_lossy(trim_nul(bytes)))
+}
+
+#[cfg(feature = "serde")]
+pub fn deserialize<'de, 'a, D: Deserializer<'de>>(deserializer: D)
+                                                   -> Result<Cow<'a, [u8]>, D::Error> {
+    String::deserialize(deserializer).map(|s| Cow::Owned(s.into_bytes()))
+}
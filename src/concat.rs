@@ -0,0 +1,105 @@
+//! Merging several demos into one.
+
+use byte_string::trim_nul;
+use errors::*;
+use parse::directory::ENTRY_TYPE_LOADING;
+use types::*;
+
+impl<'a> Demo<'a> {
+    /// Merges the demos into a single demo, one after another.
+    ///
+    /// The demos must have the same network protocol and game directory. See `append()` for
+    /// details on merging.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::{BufWriter, Read};
+    ///
+    /// let mut files = Vec::new();
+    /// for filename in &["c1a0.dem", "c1a0d.dem", "c1a0a.dem"] {
+    ///     let mut bytes = Vec::new();
+    ///     File::open(filename)?.read_to_end(&mut bytes)?;
+    ///     files.push(bytes);
+    /// }
+    ///
+    /// let mut demos = Vec::new();
+    /// for bytes in &files {
+    ///     demos.push(hldemo::Demo::parse(bytes)?);
+    /// }
+    ///
+    /// let demo = hldemo::Demo::concat(demos)?;
+    /// demo.write(BufWriter::new(File::create("merged.dem")?))?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn concat<I: IntoIterator<Item = Demo<'a>>>(demos: I) -> Result<Demo<'a>> {
+        let mut demos = demos.into_iter();
+        let mut demo = demos.next().ok_or(ErrorKind::NoDemos)?;
+
+        for other in demos {
+            demo.check_compatible(&other)?;
+            demo.append(other);
+        }
+
+        Ok(demo)
+    }
+
+    /// Checks that the other demo has the same network protocol and game directory.
+    pub fn check_compatible(&self, other: &Demo) -> Result<()> {
+        if self.header.net_protocol != other.header.net_protocol {
+            bail!(ErrorKind::NetProtocolMismatch(self.header.net_protocol,
+                                                 other.header.net_protocol));
+        }
+
+        let game_dir = trim_nul(&self.header.game_dir);
+        let other_game_dir = trim_nul(&other.header.game_dir);
+        if game_dir != other_game_dir {
+            bail!(ErrorKind::GameDirMismatch(String::from_utf8_lossy(game_dir).into_owned(),
+                                             String::from_utf8_lossy(other_game_dir)
+                                                 .into_owned()));
+        }
+
+        Ok(())
+    }
+
+    /// Appends the directory entries of the other demo, without checking if the demos are
+    /// compatible.
+    ///
+    /// The frames of the appended playback segments are moved to continue after the last playback
+    /// frame of this demo, both in time and in frame numbers. The loading segments are appended
+    /// as is. The header of this demo is kept, and the directory is rebuilt.
+    pub fn append(&mut self, other: Demo<'a>) {
+        let (time, frame) = self.directory
+                                .entries
+                                .iter()
+                                .rev()
+                                .filter(|entry| entry.entry_type != ENTRY_TYPE_LOADING)
+                                .find_map(|entry| entry.frames.last())
+                                .map_or((0f32, 0), |last| (last.time, last.frame + 1));
+
+        for mut entry in other.directory.entries {
+            if entry.entry_type != ENTRY_TYPE_LOADING {
+                for f in &mut entry.frames {
+                    f.time += time;
+                    f.frame += frame;
+                }
+            }
+
+            self.directory.entries.push(entry);
+        }
+
+        self.rebuild_directory();
+    }
+}
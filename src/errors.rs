@@ -1,12 +1,18 @@
 //! An error type wrapping nom's parsing errors and some glue between the two.
 
+use std::error;
+use std::fmt;
+
 use nom::{self, Context, Needed};
 
+use netmsg;
 use parse;
 
 error_chain! {
     foreign_links {
         ParseError(parse::Error);
+        NetMsgError(netmsg::Error);
+        Io(::std::io::Error);
     }
 
     errors {
@@ -15,30 +21,167 @@ error_chain! {
             display("need {} more bytes", count.map(|c| format!("{}", c))
                                                .unwrap_or_else(|| "(unknown)".to_string())),
         }
+        FieldTooLong(field: &'static str, length: usize, max: usize) {
+            description("field too long"),
+            display("field `{}` is {} bytes long (expected at most {})", field, length, max),
+        }
+        InvalidEntryIndex(index: usize, count: usize) {
+            description("invalid directory entry index"),
+            display("invalid directory entry index {} (there are {} entries)", index, count),
+        }
+        HltvDemo {
+            description("HLTV demo"),
+            display("the demo was recorded by an HLTV proxy, which doesn't record player input"),
+        }
+        EmptyRange {
+            description("empty range"),
+            display("the range doesn't contain any frames"),
+        }
+        NoFrames {
+            description("no frames"),
+            display("no frames could be recovered from the demo"),
+        }
+        NoDemos {
+            description("no demos"),
+            display("no demos to merge"),
+        }
+        NetProtocolMismatch(expected: i32, found: i32) {
+            description("net protocol mismatch"),
+            display("net protocol mismatch: expected {}, found {}", expected, found),
+        }
+        GameDirMismatch(expected: String, found: String) {
+            description("game directory mismatch"),
+            display("game directory mismatch: expected `{}`, found `{}`", expected, found),
+        }
     }
 }
 
-impl<I> From<Context<I, parse::Error>> for Error {
-    fn from(err: Context<I, parse::Error>) -> Self {
-        let v = match err {
-            Context::Code(i, kind) => vec![(i, kind)],
-            Context::List(vec) => vec,
+/// The position in the demo where an error occurred.
+///
+/// It is attached to parse errors as the innermost cause and can be retrieved with
+/// `Error::position()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// The byte offset from the start of the demo.
+    pub offset: usize,
+    /// The index of the directory entry, if the error occurred in the frames.
+    pub entry: Option<usize>,
+    /// The index of the frame in the directory entry, if the error occurred in the frames.
+    pub frame: Option<usize>,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at offset {} (0x{:X})", self.offset, self.offset)?;
+        if let Some(entry) = self.entry {
+            write!(f, ", directory entry {}", entry)?;
+        }
+        if let Some(frame) = self.frame {
+            write!(f, ", frame {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Position {}
+
+impl Position {
+    /// Creates an error of the given kind at this position.
+    pub(crate) fn error<K: Into<ErrorKind>>(self, kind: K) -> Error {
+        Error::with_chain(self, kind)
+    }
+}
+
+impl Error {
+    /// Returns the position in the demo where the error occurred, if it's known.
+    ///
+    /// The position is known for errors returned from parsing a whole demo, from `FrameIter` and
+    /// from `DemoReader`.
+    pub fn position(&self) -> Option<Position> {
+        let mut next: Option<&(dyn error::Error + 'static)> = match self.1.next_error {
+            Some(ref err) => Some(&**err),
+            None => None,
         };
 
-        let mut iter = v.into_iter().filter_map(|(_, x)| {
-                                        if let nom::ErrorKind::Custom(inner) = x {
-                                            Some(inner)
-                                        } else {
-                                            None
-                                        }
-                                    });
+        while let Some(err) = next {
+            if let Some(position) = err.downcast_ref::<Position>() {
+                return Some(*position);
+            }
 
-        let mut err = Error::from(iter.next().unwrap());
-        for parse_error in iter {
-            err = Error::with_chain(err, Error::from(parse_error));
+            next = err.source();
         }
 
-        err
+        None
+    }
+
+    /// Converts a nom error, attaching the position where it occurred.
+    pub(crate) fn from_nom_at<I>(err: nom::Err<I, parse::Error>, position: Position) -> Self {
+        match err {
+            nom::Err::Incomplete(Needed::Size(count)) => {
+                position.error(ErrorKind::NeedMoreBytes(Some(count)))
+            }
+            nom::Err::Incomplete(Needed::Unknown) => {
+                position.error(ErrorKind::NeedMoreBytes(None))
+            }
+            nom::Err::Error(err) | nom::Err::Failure(err) => from_context(err, Some(position)),
+        }
+    }
+}
+
+/// Returns the offset into `input` where the nom error occurred.
+///
+/// `input` must be the slice which was given to the parser.
+pub(crate) fn nom_offset(err: &nom::Err<&[u8], parse::Error>, input: &[u8]) -> usize {
+    let rest = match *err {
+        nom::Err::Incomplete(_) => return input.len(),
+        nom::Err::Error(Context::Code(i, _)) | nom::Err::Failure(Context::Code(i, _)) => i,
+        nom::Err::Error(Context::List(ref v)) | nom::Err::Failure(Context::List(ref v)) => {
+            match v.first() {
+                Some(&(i, _)) => i,
+                None => return 0,
+            }
+        }
+    };
+
+    // The remaining input is a subslice of the input.
+    let start = input.as_ptr() as usize;
+    let offset = (rest.as_ptr() as usize).wrapping_sub(start);
+    if offset <= input.len() {
+        offset
+    } else {
+        0
+    }
+}
+
+fn from_context<I>(err: Context<I, parse::Error>, position: Option<Position>) -> Error {
+    let v = match err {
+        Context::Code(i, kind) => vec![(i, kind)],
+        Context::List(vec) => vec,
+    };
+
+    let mut iter = v.into_iter().filter_map(|(_, x)| {
+                                    if let nom::ErrorKind::Custom(inner) = x {
+                                        Some(inner)
+                                    } else {
+                                        None
+                                    }
+                                });
+
+    let first = iter.next().unwrap();
+    let mut err = match position {
+        Some(position) => position.error(ErrorKind::ParseError(first)),
+        None => Error::from(first),
+    };
+    for parse_error in iter {
+        err = Error::with_chain(err, Error::from(parse_error));
+    }
+
+    err
+}
+
+impl<I> From<Context<I, parse::Error>> for Error {
+    fn from(err: Context<I, parse::Error>) -> Self {
+        from_context(err, None)
     }
 }
 
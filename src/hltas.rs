@@ -0,0 +1,203 @@
+//! Exporting the player inputs as a Bunnymod XT TAS script (`.hltas`).
+//!
+//! Every engine frame of the playback segments becomes a frame line with the movement keys and
+//! the action keys deduced from `UserCmd`, the frame time and the view angles. Identical
+//! consecutive lines are merged by increasing the repeat count.
+//!
+//! Console commands are added to the frame they were executed in, except for the `+` and `-`
+//! commands of the buttons, which are already represented by the keys. Impulses are added as
+//! `impulse` commands.
+//!
+//! HLTV demos don't record the player input, so `ErrorKind::HltvDemo` is returned for them.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::{BufWriter, Read};
+//!
+//! let mut bytes = Vec::new();
+//! let mut f = File::open("demo.dem")?;
+//! f.read_to_end(&mut bytes);
+//!
+//! let demo = hldemo::Demo::parse(&bytes)?;
+//! hldemo::hltas::write(&demo, BufWriter::new(File::create("demo.hltas")?))?;
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::io::Write;
+
+use byte_string::lossy_string;
+use errors::*;
+use parse::directory::ENTRY_TYPE_LOADING;
+use types::*;
+
+/// The version of the script format.
+pub const VERSION: u32 = 1;
+
+/// A frame line of the script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameLine {
+    /// The movement keys: forward, left, right, back, up, down.
+    pub movement: [bool; 6],
+    /// The action keys: jump, duck, use, attack, secondary attack, reload.
+    pub actions: [bool; 6],
+    pub frametime: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub repeats: u32,
+    /// The console commands executed in the frame.
+    pub commands: Vec<String>,
+}
+
+/// Returns the frame time of the command.
+///
+/// `RefParams::frametime` is used when it matches `UserCmd::msec`, which holds the frame time
+/// in whole milliseconds. Otherwise the frame time is taken from `UserCmd::msec`.
+pub fn frametime(info: &NetMsgInfo) -> f32 {
+    let frametime = info.ref_params.frametime;
+    let msec = f32::from(info.usercmd.msec);
+
+    if frametime > 0f32 && (frametime * 1000f32 - msec).abs() < 1f32 {
+        frametime
+    } else {
+        msec / 1000f32
+    }
+}
+
+/// Returns `true` if the command presses or releases a button.
+fn is_button_command(command: &str) -> bool {
+    command.starts_with('+') || command.starts_with('-')
+}
+
+impl FrameLine {
+    /// Creates a frame line from the command in the NetMsg frame.
+    pub fn from_netmsg(data: &NetMsgData) -> Self {
+        let cmd = &data.info.usercmd;
+        let buttons = cmd.buttons();
+
+        let mut commands = Vec::new();
+        if cmd.impulse != 0 {
+            commands.push(format!("impulse {}", cmd.impulse));
+        }
+
+        FrameLine { movement: [cmd.forwardmove > 0f32,
+                               cmd.sidemove < 0f32,
+                               cmd.sidemove > 0f32,
+                               cmd.forwardmove < 0f32,
+                               cmd.upmove > 0f32,
+                               cmd.upmove < 0f32],
+                    actions: [buttons.contains(Buttons::JUMP),
+                              buttons.contains(Buttons::DUCK),
+                              buttons.contains(Buttons::USE),
+                              buttons.contains(Buttons::ATTACK),
+                              buttons.contains(Buttons::ATTACK2),
+                              buttons.contains(Buttons::RELOAD)],
+                    frametime: frametime(&data.info),
+                    yaw: cmd.viewangles[1],
+                    pitch: cmd.viewangles[0],
+                    repeats: 1,
+                    commands }
+    }
+
+    /// Returns `true` if the lines only differ in the repeat count.
+    fn can_merge(&self, other: &FrameLine) -> bool {
+        self.movement == other.movement
+        && self.actions == other.actions
+        && self.frametime == other.frametime
+        && self.yaw == other.yaw
+        && self.pitch == other.pitch
+        && self.commands == other.commands
+    }
+
+    fn write<W: Write>(&self, mut w: W) -> Result<()> {
+        let keys = |keys: &[bool; 6], names: &[u8; 6]| -> String {
+            keys.iter()
+                .zip(names)
+                .map(|(&held, &name)| if held { name as char } else { '-' })
+                .collect()
+        };
+
+        write!(w,
+               "----------|{}|{}|{}|{}|{}|{}",
+               keys(&self.movement, b"flrbud"),
+               keys(&self.actions, b"jdu12r"),
+               self.frametime,
+               self.yaw,
+               self.pitch,
+               self.repeats)?;
+
+        if !self.commands.is_empty() {
+            write!(w, "|{}", self.commands.join(";"))?;
+        }
+
+        writeln!(w)?;
+        Ok(())
+    }
+}
+
+/// Returns the frame lines for the playback segments of the demo.
+///
+/// Fails with `ErrorKind::HltvDemo` for HLTV demos.
+pub fn frame_lines(demo: &Demo) -> Result<Vec<FrameLine>> {
+    if demo.is_hltv() {
+        bail!(ErrorKind::HltvDemo);
+    }
+
+    let mut lines: Vec<FrameLine> = Vec::new();
+    let mut commands = Vec::new();
+
+    for entry in &demo.directory.entries {
+        if entry.entry_type == ENTRY_TYPE_LOADING {
+            continue;
+        }
+
+        for engine_frame in entry.engine_frames() {
+            // The console commands are recorded before the NetMsg frames of the engine frame.
+            for frame in engine_frame.frames {
+                if let FrameData::ConsoleCommand(ref data) = frame.data {
+                    let command = lossy_string(&data.command).trim().to_string();
+
+                    if !command.is_empty() && !is_button_command(&command) {
+                        commands.push(command);
+                    }
+                }
+            }
+
+            if let Some((_, data)) = engine_frame.netmsg() {
+                let mut line = FrameLine::from_netmsg(data);
+                line.commands.splice(0..0, commands.drain(..));
+
+                match lines.last_mut() {
+                    Some(last) if last.can_merge(&line) => last.repeats += 1,
+                    _ => lines.push(line),
+                }
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Writes the player inputs of the demo as a TAS script.
+pub fn write<W: Write>(demo: &Demo, mut output: W) -> Result<()> {
+    writeln!(output, "version {}", VERSION)?;
+    writeln!(output, "frames")?;
+
+    for line in frame_lines(demo)? {
+        line.write(&mut output)?;
+    }
+
+    Ok(())
+}
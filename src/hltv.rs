@@ -0,0 +1,185 @@
+//! HLTV demos.
+//!
+//! Demos recorded by HLTV proxies are recorded from the point of view of a spectator: the
+//! spectator flag in `RefParams` is set, `RefParams` holds the camera rather than a player and
+//! `UserCmd` doesn't contain any player input. The analysis modules which rely on `RefParams` and
+//! `UserCmd`, such as `trajectory`, `strafe` and `hltas`, return `ErrorKind::HltvDemo` for them.
+//!
+//! Instead, the player positions are taken from the entity states in the network messages, which
+//! the proxy receives for every player. `player_trajectories()` decodes them with
+//! `netmsg::StateTracker`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! let mut bytes = Vec::new();
+//! let mut f = File::open("hltv.dem")?;
+//! f.read_to_end(&mut bytes);
+//!
+//! let demo = hldemo::Demo::parse(&bytes)?;
+//! if demo.is_hltv() {
+//!     for player in hldemo::hltv::player_trajectories(&demo)? {
+//!         if let Some(point) = player.trajectory.points.last() {
+//!             println!("player {}: {:?}", player.player, point.origin);
+//!         }
+//!     }
+//! }
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use errors::*;
+use netmsg::delta::Delta;
+use netmsg::state::{EntityState, StateTracker};
+use parse::directory::ENTRY_TYPE_LOADING;
+use trajectory::{Point, Trajectory};
+use types::*;
+
+/// The `usehull` value of the ducked player hull.
+pub const DUCKED_HULL: i64 = 1;
+
+/// The trajectory of a player, from the entity states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerTrajectory {
+    /// The entity index of the player.
+    pub player: u16,
+    pub trajectory: Trajectory,
+}
+
+/// Returns the NetMsg data of the frames.
+fn netmsgs<'a, 'b>(frames: &'b [Frame<'a>]) -> impl Iterator<Item = &'b NetMsgData<'a>> {
+    frames.iter().filter_map(|frame| match frame.data {
+                                 FrameData::NetMsg((_, ref data)) => Some(data),
+                                 _ => None,
+                             })
+}
+
+/// Returns `true` if there are NetMsg frames and the spectator flag is set in every one of them.
+fn all_spectator<'a, 'b, I>(netmsgs: I) -> bool
+    where 'a: 'b,
+          I: Iterator<Item = &'b NetMsgData<'a>>
+{
+    let mut netmsgs = netmsgs.peekable();
+    netmsgs.peek().is_some() && netmsgs.all(|data| data.info.ref_params.spectator != 0)
+}
+
+impl<'a> Demo<'a> {
+    /// Returns `true` if the demo was recorded by an HLTV proxy.
+    ///
+    /// HLTV demos are recognized by the spectator flag in `RefParams`, which is set in every
+    /// playback NetMsg frame. Demos recorded by a player who spectated the whole time are
+    /// recognized as HLTV demos as well.
+    pub fn is_hltv(&self) -> bool {
+        all_spectator(self.directory
+                          .entries
+                          .iter()
+                          .filter(|entry| entry.entry_type != ENTRY_TYPE_LOADING)
+                          .flat_map(|entry| netmsgs(&entry.frames)))
+    }
+}
+
+impl<'a> DirectoryEntry<'a> {
+    /// Returns `true` if the directory entry was recorded by an HLTV proxy.
+    ///
+    /// See `Demo::is_hltv()`.
+    pub fn is_hltv(&self) -> bool {
+        all_spectator(netmsgs(&self.frames))
+    }
+}
+
+#[inline]
+fn integer(fields: &Delta, name: &str) -> i64 {
+    fields.get(name).and_then(|x| x.as_i64()).unwrap_or(0)
+}
+
+/// Creates a point from the entity state of a player.
+///
+/// When the server doesn't send the velocity, it's estimated from the change of the origin since
+/// the previous point. The water level isn't sent and is always zero.
+fn point(time: f32, frame: i32, state: &EntityState, previous: Option<&Point>) -> Point {
+    let origin = state.origin();
+    let velocity = match previous {
+        Some(previous) if !state.fields.contains_key("velocity[0]") && time > previous.time => {
+            let dt = time - previous.time;
+            [(origin[0] - previous.origin[0]) / dt,
+             (origin[1] - previous.origin[1]) / dt,
+             (origin[2] - previous.origin[2]) / dt]
+        }
+        _ => state.velocity(),
+    };
+
+    Point { time,
+            frame,
+            origin,
+            velocity,
+            speed: velocity[0].hypot(velocity[1]),
+            viewangles: state.angles(),
+            onground: integer(&state.fields, "onground") != 0,
+            waterlevel: 0,
+            ducking: integer(&state.fields, "usehull") == DUCKED_HULL }
+}
+
+/// Returns the trajectories of the players in every playback segment, from the entity states.
+///
+/// The trajectories are ordered by directory entry and then by player. An engine frame can be
+/// recorded as several NetMsg frames with the same frame number, in which case the state after the
+/// last one is used.
+///
+/// In demos recorded by a player, the server usually doesn't send the origin of the recording
+/// player's own entity, so `trajectory::trajectories()` should be used for that player instead.
+pub fn player_trajectories(demo: &Demo) -> Result<Vec<PlayerTrajectory>> {
+    let mut tracker = StateTracker::new(demo.header.net_protocol)?;
+    let mut trajectories = Vec::new();
+
+    for (i, entry) in demo.directory.entries.iter().enumerate() {
+        let mut points: BTreeMap<u16, Vec<Point>> = BTreeMap::new();
+
+        for frame in &entry.frames {
+            if let FrameData::NetMsg((_, ref data)) = frame.data {
+                tracker.update(data)?;
+
+                if entry.entry_type == ENTRY_TYPE_LOADING {
+                    continue;
+                }
+
+                let players = tracker.entities()
+                                     .iter()
+                                     .filter(|&(&index, _)| tracker.decoder().is_player(index));
+
+                for (&index, state) in players {
+                    let points = points.entry(index).or_default();
+
+                    if points.last().map(|p| p.frame) == Some(frame.frame) {
+                        points.pop();
+                    }
+
+                    let point = point(frame.time, frame.frame, state, points.last());
+                    points.push(point);
+                }
+            }
+        }
+
+        for (player, points) in points {
+            trajectories.push(PlayerTrajectory { player,
+                                                 trajectory: Trajectory { entry: i,
+                                                                          points } });
+        }
+    }
+
+    Ok(trajectories)
+}
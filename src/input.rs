@@ -0,0 +1,107 @@
+//! Button press and release events.
+//!
+//! Every NetMsg frame stores the buttons the player held in `UserCmd::buttons`. `ButtonTracker`
+//! compares the buttons of consecutive frames and reports the buttons which were pressed or
+//! released in between.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! let mut bytes = Vec::new();
+//! let mut f = File::open("demo.dem")?;
+//! f.read_to_end(&mut bytes);
+//!
+//! let demo = hldemo::Demo::parse(&bytes)?;
+//! for event in hldemo::input::button_events(&demo.directory.entries[1]) {
+//!     println!("{}: {:?} {}",
+//!              event.time,
+//!              event.button,
+//!              if event.pressed { "pressed" } else { "released" });
+//! }
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use types::*;
+
+/// A button press or release.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ButtonEvent {
+    pub time: f32,
+    pub frame: i32,
+    /// The button, a single flag.
+    pub button: Buttons,
+    /// `true` if the button was pressed, `false` if it was released.
+    pub pressed: bool,
+}
+
+/// Tracks the held buttons and produces events when they change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ButtonTracker {
+    buttons: Buttons,
+}
+
+impl ButtonTracker {
+    /// Creates a tracker with no buttons held.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the buttons held after the last update.
+    #[inline]
+    pub fn buttons(&self) -> Buttons {
+        self.buttons
+    }
+
+    /// Updates the held buttons and returns the events for the buttons which changed, in the
+    /// order of the button bits.
+    pub fn update(&mut self, time: f32, frame: i32, buttons: Buttons) -> Vec<ButtonEvent> {
+        let changed = self.buttons ^ buttons;
+        self.buttons = buttons;
+
+        (0..16).map(|i| Buttons::from_bits_truncate(1 << i))
+               .filter(|&button| changed.contains(button))
+               .map(|button| {
+                        ButtonEvent { time,
+                                      frame,
+                                      button,
+                                      pressed: buttons.contains(button) }
+                    })
+               .collect()
+    }
+
+    /// Updates the held buttons from the frame if it's a NetMsg frame.
+    ///
+    /// Returns the events for the buttons which changed.
+    pub fn update_frame(&mut self, frame: &Frame) -> Vec<ButtonEvent> {
+        match frame.data {
+            FrameData::NetMsg((_, ref data)) => {
+                self.update(frame.time, frame.frame, data.info.usercmd.buttons())
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns the button events in the NetMsg frames of the directory entry.
+pub fn button_events(entry: &DirectoryEntry) -> Vec<ButtonEvent> {
+    let mut tracker = ButtonTracker::new();
+    entry.frames
+         .iter()
+         .flat_map(|frame| tracker.update_frame(frame))
+         .collect()
+}
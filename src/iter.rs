@@ -0,0 +1,244 @@
+//! Lazy iteration over demo frames.
+
+use nom;
+
+use errors::*;
+use parse;
+use parse::frame::{frame_data, frame_header, FrameType};
+use types::*;
+
+/// An iterator over the frames of a directory entry.
+///
+/// Frames are parsed one at a time as the iterator advances, so the frames of a directory entry
+/// never need to be in memory all at once. The iterator yields every frame up to and including the
+/// `NextSection` frame which ends the entry. After an error is returned the iterator is exhausted.
+///
+/// Errors carry the position of the frame which failed to parse, see `Error::position()`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::error::Error;
+/// #
+/// # fn try_main() -> Result<(), Box<Error>> {
+/// extern crate hldemo;
+///
+/// use std::fs::File;
+/// use std::io::Read;
+///
+/// let mut bytes = Vec::new();
+/// let mut f = File::open("demo.dem")?;
+/// f.read_to_end(&mut bytes);
+///
+/// let demo = hldemo::Demo::parse_without_frames(&bytes)?;
+/// for frame in demo.directory.entries[0].frame_iter(&bytes) {
+///     let frame = frame?;
+///     println!("{}", frame.time);
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameIter<'a> {
+    input: &'a [u8],
+    offset: usize,
+    done: bool,
+    base: usize,
+    entry: Option<usize>,
+    frame: usize,
+}
+
+impl<'a> FrameIter<'a> {
+    /// Creates an iterator over the frames at the start of `input`.
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_offset(input, 0)
+    }
+
+    /// Creates an iterator over the frames starting at `offset` into `input`.
+    ///
+    /// If `offset` is past the end of `input`, the iterator returns a single error.
+    pub fn with_offset(input: &'a [u8], offset: usize) -> Self {
+        FrameIter { input,
+                    offset,
+                    done: false,
+                    base: 0,
+                    entry: None,
+                    frame: 0 }
+    }
+
+    /// Sets the directory entry index reported in error positions.
+    pub fn for_entry(mut self, entry: usize) -> Self {
+        self.entry = Some(entry);
+        self
+    }
+
+    /// Sets the offset of `input` from the start of the demo, used for error positions.
+    pub(crate) fn with_base(mut self, base: usize) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Returns the offset into the input of the next frame.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the position of the next frame.
+    fn frame_position(&self) -> Position {
+        Position { offset: self.base + self.offset,
+                   entry: self.entry,
+                   frame: Some(self.frame) }
+    }
+
+    fn next_frame(&mut self) -> Result<Frame<'a>> {
+        if self.offset > self.input.len() {
+            let count = self.offset - self.input.len();
+            return Err(self.frame_position().error(ErrorKind::NeedMoreBytes(Some(count))));
+        }
+
+        let input = &self.input[self.offset..];
+        let error = |err: nom::Err<&[u8], parse::Error>| {
+            let mut position = self.frame_position();
+            position.offset += nom_offset(&err, input);
+            Error::from_nom_at(err, position)
+        };
+
+        let (rest, header) = frame_header(input).map_err(&error)?;
+        let (rest, data) = frame_data(rest, header.frame_type).map_err(&error)?;
+
+        if header.frame_type == FrameType::NextSection {
+            self.done = true;
+        }
+
+        self.offset += input.len() - rest.len();
+        self.frame += 1;
+
+        Ok(Frame { time: header.time,
+                   frame: header.frame,
+                   data })
+    }
+}
+
+impl<'a> Iterator for FrameIter<'a> {
+    type Item = Result<Frame<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let rv = self.next_frame().map_err(|err| {
+                                              self.done = true;
+                                              let frames = Error::from(parse::Error::Frames);
+                                              Error::with_chain(err, frames)
+                                          });
+        Some(rv)
+    }
+}
+
+impl<'a> DirectoryEntry<'a> {
+    /// Returns a lazy iterator over the entry's frames.
+    ///
+    /// `input` must be the whole demo, the same one the directory was parsed from.
+    pub fn frame_iter<'b>(&self, input: &'b [u8]) -> FrameIter<'b> {
+        FrameIter::with_offset(input, self.offset as usize)
+    }
+}
+
+/// The frames recorded during one engine frame.
+///
+/// Every engine frame is recorded as one or more NetMsg frames with the same frame number, along
+/// with the other frames recorded during it, such as console commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EngineFrame<'b, 'a: 'b> {
+    /// The frames with the frame number, in the order they were recorded.
+    pub frames: &'b [Frame<'a>],
+}
+
+impl<'b, 'a: 'b> EngineFrame<'b, 'a> {
+    /// Returns the frame number.
+    #[inline]
+    pub fn frame(&self) -> i32 {
+        self.frames[0].frame
+    }
+
+    /// Returns the first NetMsg frame and its data, or `None` if there are no NetMsg frames.
+    ///
+    /// The first NetMsg frame describes the engine frame, the following ones only carry the
+    /// network messages which didn't fit into it.
+    pub fn netmsg(&self) -> Option<(&'b Frame<'a>, &'b NetMsgData<'a>)> {
+        self.frames
+            .iter()
+            .filter_map(|frame| match frame.data {
+                            FrameData::NetMsg((_, ref data)) => Some((frame, data)),
+                            _ => None,
+                        })
+            .next()
+    }
+}
+
+/// An iterator over the engine frames of a directory entry, see `DirectoryEntry::engine_frames()`.
+#[derive(Debug, Clone)]
+pub struct EngineFrames<'b, 'a: 'b> {
+    frames: &'b [Frame<'a>],
+}
+
+impl<'b, 'a: 'b> Iterator for EngineFrames<'b, 'a> {
+    type Item = EngineFrame<'b, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let number = self.frames.first()?.frame;
+        let count = self.frames
+                        .iter()
+                        .position(|frame| frame.frame != number)
+                        .unwrap_or(self.frames.len());
+
+        let (frames, rest) = self.frames.split_at(count);
+        self.frames = rest;
+        Some(EngineFrame { frames })
+    }
+}
+
+impl<'a> DirectoryEntry<'a> {
+    /// Returns an iterator over the engine frames, grouping consecutive frames with the same frame
+    /// number.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::Read;
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut f = File::open("demo.dem")?;
+    /// f.read_to_end(&mut bytes);
+    ///
+    /// let demo = hldemo::Demo::parse(&bytes)?;
+    /// for engine_frame in demo.directory.entries[1].engine_frames() {
+    ///     if let Some((frame, data)) = engine_frame.netmsg() {
+    ///         println!("{}: {}", frame.time, data.info.ref_params.frametime);
+    ///     }
+    /// }
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn engine_frames<'b>(&'b self) -> EngineFrames<'b, 'a> {
+        EngineFrames { frames: &self.frames }
+    }
+}
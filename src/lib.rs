@@ -3,7 +3,27 @@
 //!
 //! Supports parsing demos completely as well as just header and directory when the frame data
 //! isn't needed. Exports both raw nom parsing functions and wrappers which hide all nom-related
-//! types and provide convenient errors.
+//! types and provide convenient errors. Frames of a directory entry can also be parsed lazily one
+//! at a time with `FrameIter`, and demos can be read from any `Read + Seek` source with
+//! `DemoReader`. Demos can also be written back into the demo file format, and the network
+//! messages stored in NetMsg frames can be decoded with the `netmsg` module. Truncated and
+//! corrupted demos can be parsed with `Demo::parse_lenient()`, which recovers as many frames as
+//! possible, and `Demo::repair()` rebuilds the directory of such demos so that they can be
+//! written back as valid demos. Clips can be cut out of demos with `Demo::trim_time()` and
+//! `Demo::trim_frames()`, and several demos can be merged into one with `Demo::concat()`. Runs
+//! recorded across several demos can be timed with the `timing` module, and the player movement
+//! can be extracted with the `trajectory` module and analyzed with the `strafe` module. Button
+//! presses and releases can be followed with the `input` module, and the player inputs can be
+//! exported as a TAS script with the `hltas` module. Demos recorded by HLTV proxies are recognized
+//! with `Demo::is_hltv()`, and the `hltv` module extracts the player positions from the entity
+//! states. New demos can be created frame by frame with `DemoBuilder`. Files of unknown origin can
+//! be inspected with `probe()`, which reads what it can from the header and the directory.
+//!
+//! With the `rayon` feature enabled, the directory entries are parsed in parallel, and
+//! `Demo::parse_parallel()` decodes the frames in parallel chunks.
+//!
+//! With the `serde` feature enabled, the demo types implement `Serialize` and `Deserialize`, with
+//! byte string fields such as `Header::map_name` serialized as strings.
 //!
 //! # Examples
 //! Check the `examples` folder for more complete program examples which output various data from
@@ -35,18 +55,54 @@
 #![doc(html_root_url = "https://docs.rs/hldemo/0.3.0")]
 #![recursion_limit = "1024"]
 
+#[macro_use]
+extern crate bitflags;
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
 extern crate nom;
 #[macro_use]
 extern crate quick_error;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
+mod builder;
+mod byte_string;
+mod concat;
 pub mod errors;
+pub mod hltas;
+pub mod hltv;
+pub mod input;
+mod iter;
+pub mod netmsg;
+#[cfg(feature = "rayon")]
+mod parallel;
 pub mod parse;
+mod probe;
+mod reader;
+mod recover;
+mod repair;
+pub mod strafe;
+pub mod timing;
+pub mod trajectory;
+mod trim;
 mod types;
+pub mod write;
 
 #[cfg(test)]
 mod tests;
 
+pub use builder::*;
+pub use byte_string::{lossy_string, trim_nul};
+pub use iter::*;
+#[cfg(feature = "rayon")]
+pub use parallel::*;
+pub use parse::directory::{ENTRY_TYPE_LOADING, ENTRY_TYPE_PLAYBACK};
+pub use probe::*;
+pub use reader::*;
+pub use recover::*;
 pub use types::*;
@@ -0,0 +1,249 @@
+//! A reader for the bit-packed buffers used by the engine network messages.
+
+use super::Error;
+
+/// A reader over a byte buffer which can read both bit-packed and byte-aligned values.
+///
+/// Bits are read starting from the least significant bit of every byte, same as the engine does.
+/// Byte-aligned reads require the reader to be at a byte boundary, which is the case between
+/// messages and after calling `align()`.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Creates a reader over the given buffer.
+    pub fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    /// Returns the current position in bits.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bits left.
+    #[inline]
+    pub fn remaining_bits(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    /// Returns `true` if there's nothing left to read.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.remaining_bits() == 0
+    }
+
+    /// Skips to the start of the next byte, unless already at a byte boundary.
+    #[inline]
+    pub fn align(&mut self) {
+        self.position = (self.position + 7) & !7;
+    }
+
+    #[inline]
+    fn check(&self, bits: usize) -> Result<(), Error> {
+        if self.remaining_bits() < bits {
+            Err(Error::UnexpectedEnd)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads a single bit.
+    #[inline]
+    pub fn read_bit(&mut self) -> Result<bool, Error> {
+        self.read_bits(1).map(|x| x == 1)
+    }
+
+    /// Reads up to 32 bits as an unsigned value.
+    ///
+    /// Fails with `Error::InvalidBitCount` if `count` is above 32.
+    pub fn read_bits(&mut self, count: u32) -> Result<u32, Error> {
+        if count > 32 {
+            return Err(Error::InvalidBitCount(count));
+        }
+        self.check(count as usize)?;
+
+        let mut value = 0u64;
+        let mut read = 0;
+        while read < count {
+            let byte = self.data[self.position / 8];
+            let offset = (self.position % 8) as u32;
+            let take = (8 - offset).min(count - read);
+            let bits = (u64::from(byte) >> offset) & ((1 << take) - 1);
+
+            value |= bits << read;
+            read += take;
+            self.position += take as usize;
+        }
+
+        Ok(value as u32)
+    }
+
+    /// Returns the next bits without advancing the reader.
+    pub fn peek_bits(&self, count: u32) -> Result<u32, Error> {
+        self.clone().read_bits(count)
+    }
+
+    /// Reads a value stored as a sign bit followed by `count - 1` bits of magnitude.
+    ///
+    /// Fails with `Error::InvalidBitCount` if `count` is zero or above 32.
+    pub fn read_signed_bits(&mut self, count: u32) -> Result<i32, Error> {
+        if count == 0 || count > 32 {
+            return Err(Error::InvalidBitCount(count));
+        }
+
+        let negative = self.read_bit()?;
+        let value = self.read_bits(count - 1)? as i32;
+        Ok(if negative { -value } else { value })
+    }
+
+    /// Reads an angle stored in `count` bits.
+    #[inline]
+    pub fn read_bit_angle(&mut self, count: u32) -> Result<f32, Error> {
+        let value = self.read_bits(count)?;
+        Ok(value as f32 * (360f32 / (1u64 << count) as f32))
+    }
+
+    /// Reads a bit-packed coordinate.
+    pub fn read_bit_coord(&mut self) -> Result<f32, Error> {
+        let has_integer = self.read_bit()?;
+        let has_fraction = self.read_bit()?;
+
+        if !has_integer && !has_fraction {
+            return Ok(0f32);
+        }
+
+        let negative = self.read_bit()?;
+        let integer = if has_integer { self.read_bits(12)? } else { 0 };
+        let fraction = if has_fraction { self.read_bits(3)? } else { 0 };
+
+        let value = integer as f32 + fraction as f32 / 8f32;
+        Ok(if negative { -value } else { value })
+    }
+
+    /// Reads a vector of bit-packed coordinates where every component is optional.
+    pub fn read_bit_vec3_coord(&mut self) -> Result<[f32; 3], Error> {
+        let present = [self.read_bit()?, self.read_bit()?, self.read_bit()?];
+
+        let mut rv = [0f32; 3];
+        for (x, &present) in rv.iter_mut().zip(&present) {
+            if present {
+                *x = self.read_bit_coord()?;
+            }
+        }
+
+        Ok(rv)
+    }
+
+    /// Reads a null-terminated string made of 8-bit characters at any bit position.
+    ///
+    /// The terminator isn't included in the result.
+    pub fn read_bit_string(&mut self) -> Result<Vec<u8>, Error> {
+        let mut rv = Vec::new();
+        loop {
+            match self.read_bits(8)? as u8 {
+                0 => return Ok(rv),
+                c => rv.push(c),
+            }
+        }
+    }
+
+    /// Reads `count` bytes at any bit position.
+    pub fn read_bit_bytes(&mut self, count: usize) -> Result<Vec<u8>, Error> {
+        (0..count).map(|_| self.read_bits(8).map(|x| x as u8))
+                  .collect()
+    }
+
+    /// Reads `count` bytes. The reader must be at a byte boundary.
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        debug_assert_eq!(self.position % 8, 0);
+        self.check(count * 8)?;
+
+        let start = self.position / 8;
+        self.position += count * 8;
+        Ok(&self.data[start..start + count])
+    }
+
+    #[inline]
+    fn read_array<T: Default + AsMut<[u8]>>(&mut self) -> Result<T, Error> {
+        let mut rv = T::default();
+        {
+            let buf = rv.as_mut();
+            let len = buf.len();
+            buf.copy_from_slice(self.read_bytes(len)?);
+        }
+        Ok(rv)
+    }
+
+    /// Reads a byte.
+    #[inline]
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        self.read_bytes(1).map(|x| x[0])
+    }
+
+    /// Reads a signed byte.
+    #[inline]
+    pub fn read_i8(&mut self) -> Result<i8, Error> {
+        self.read_u8().map(|x| x as i8)
+    }
+
+    /// Reads a little-endian `u16`.
+    #[inline]
+    pub fn read_u16(&mut self) -> Result<u16, Error> {
+        self.read_array().map(u16::from_le_bytes)
+    }
+
+    /// Reads a little-endian `i16`.
+    #[inline]
+    pub fn read_i16(&mut self) -> Result<i16, Error> {
+        self.read_array().map(i16::from_le_bytes)
+    }
+
+    /// Reads a little-endian `i32`.
+    #[inline]
+    pub fn read_i32(&mut self) -> Result<i32, Error> {
+        self.read_array().map(i32::from_le_bytes)
+    }
+
+    /// Reads a little-endian `f32`.
+    #[inline]
+    pub fn read_f32(&mut self) -> Result<f32, Error> {
+        self.read_array().map(u32::from_le_bytes).map(f32::from_bits)
+    }
+
+    /// Reads a coordinate stored as a 16-bit fixed point number.
+    #[inline]
+    pub fn read_coord(&mut self) -> Result<f32, Error> {
+        self.read_i16().map(|x| f32::from(x) / 8f32)
+    }
+
+    /// Reads an angle stored in a single byte.
+    #[inline]
+    pub fn read_angle(&mut self) -> Result<f32, Error> {
+        self.read_i8().map(|x| f32::from(x) * (360f32 / 256f32))
+    }
+
+    /// Reads an angle stored in two bytes.
+    #[inline]
+    pub fn read_hires_angle(&mut self) -> Result<f32, Error> {
+        self.read_i16().map(|x| f32::from(x) * (360f32 / 65536f32))
+    }
+
+    /// Reads a null-terminated string. The reader must be at a byte boundary.
+    ///
+    /// The terminator isn't included in the result. If the buffer ends before the terminator, the
+    /// rest of the buffer is returned.
+    pub fn read_string(&mut self) -> Result<&'a [u8], Error> {
+        debug_assert_eq!(self.position % 8, 0);
+        self.check(8)?;
+
+        let rest = &self.data[self.position / 8..];
+        let len = rest.iter().position(|&c| c == 0).unwrap_or(rest.len());
+        self.position += (len + 1).min(rest.len()) * 8;
+        Ok(&rest[..len])
+    }
+}
@@ -0,0 +1,726 @@
+//! Decoding functions for the individual messages.
+
+use super::delta::META_DESCRIPTION_NAME;
+use super::*;
+
+/// Returns the data size of a temporary entity of the given type.
+///
+/// Types with variable size are handled separately.
+fn temp_entity_size(entity_type: u8) -> Option<usize> {
+    let size = match entity_type {
+        0 => 24,             // TE_BEAMPOINTS
+        1 => 20,             // TE_BEAMENTPOINT
+        2 => 6,              // TE_GUNSHOT
+        3 => 11,             // TE_EXPLOSION
+        4 => 6,              // TE_TAREXPLOSION
+        5 => 10,             // TE_SMOKE
+        6 => 12,             // TE_TRACER
+        7 => 17,             // TE_LIGHTNING
+        8 => 16,             // TE_BEAMENTS
+        9 => 6,              // TE_SPARKS
+        10 => 6,             // TE_LAVASPLASH
+        11 => 6,             // TE_TELEPORT
+        12 => 8,             // TE_EXPLOSION2
+        14 => 9,             // TE_IMPLOSION
+        15 => 19,            // TE_SPRITETRAIL
+        17 => 10,            // TE_SPRITE
+        18 => 16,            // TE_BEAMSPRITE
+        19..=21 => 24,       // TE_BEAMTORUS, TE_BEAMDISK, TE_BEAMCYLINDER
+        22 => 10,            // TE_BEAMFOLLOW
+        23 => 11,            // TE_GLOWSPRITE
+        24 => 16,            // TE_BEAMRING
+        25 => 19,            // TE_STREAK_SPLASH
+        27 => 12,            // TE_DLIGHT
+        28 => 16,            // TE_ELIGHT
+        30 | 31 => 17,       // TE_LINE, TE_BOX
+        99 => 2,             // TE_KILLBEAM
+        100 => 10,           // TE_LARGEFUNNEL
+        101 => 14,           // TE_BLOODSTREAM
+        102 => 12,           // TE_SHOWLINE
+        103 => 14,           // TE_BLOOD
+        104 => 9,            // TE_DECAL
+        105 => 5,            // TE_FIZZ
+        106 => 17,           // TE_MODEL
+        107 => 13,           // TE_EXPLODEMODEL
+        108 => 24,           // TE_BREAKMODEL
+        109 => 9,            // TE_GUNSHOTDECAL
+        110 => 17,           // TE_SPRITE_SPRAY
+        111 => 7,            // TE_ARMOR_RICOCHET
+        112 => 10,           // TE_PLAYERDECAL
+        113 | 114 => 19,     // TE_BUBBLES, TE_BUBBLETRAIL
+        115 => 12,           // TE_BLOODSPRITE
+        116 | 117 => 7,      // TE_WORLDDECAL, TE_WORLDDECALHIGH
+        118 => 9,            // TE_DECALHIGH
+        119 => 16,           // TE_PROJECTILE
+        120 => 18,           // TE_SPRAY
+        121 => 5,            // TE_PLAYERSPRITES
+        122 => 10,           // TE_PARTICLEBURST
+        123 => 13,           // TE_FIREFIELD
+        124 => 7,            // TE_PLAYERATTACHMENT
+        125 => 1,            // TE_KILLPLAYERATTACHMENTS
+        126 => 18,           // TE_MULTIGUNSHOT
+        127 => 15,           // TE_USERTRACER
+        _ => return None,
+    };
+
+    Some(size)
+}
+
+#[inline]
+fn coord3(r: &mut BitReader) -> Result<[f32; 3], Error> {
+    Ok([r.read_coord()?, r.read_coord()?, r.read_coord()?])
+}
+
+impl MessageDecoder {
+    fn description(&self, name: &str) -> Result<&DeltaDescription, Error> {
+        self.delta_descriptions
+            .get(name)
+            .ok_or_else(|| Error::UnknownDeltaDescription(name.to_string()))
+    }
+
+    fn read_delta(&self, r: &mut BitReader, name: &str) -> Result<Delta, Error> {
+        self.description(name)?.read(r)
+    }
+
+    /// Returns the name of the description used for the entity state.
+    fn entity_description(&self, index: u16, custom: bool) -> &'static str {
+        if custom {
+            "custom_entity_state_t"
+        } else if self.is_player(index) {
+            "entity_state_player_t"
+        } else {
+            "entity_state_t"
+        }
+    }
+
+    /// Decodes the next message.
+    pub(super) fn message<'a>(&mut self, r: &mut BitReader<'a>) -> Result<Message<'a>, Error> {
+        let index = r.read_u8()?;
+
+        let message = match index {
+            svc::NOP => Message::Nop,
+            svc::DISCONNECT => Message::Disconnect(r.read_string()?),
+            svc::EVENT => Message::Event(self.event(r)?),
+            svc::VERSION => Message::Version(r.read_i32()?),
+            svc::SETVIEW => Message::SetView(r.read_i16()?),
+            svc::SOUND => Message::Sound(sound(r)?),
+            svc::TIME => Message::Time(r.read_f32()?),
+            svc::PRINT => Message::Print(r.read_string()?),
+            svc::STUFFTEXT => Message::StuffText(r.read_string()?),
+            svc::SETANGLE => {
+                Message::SetAngle([r.read_hires_angle()?,
+                                   r.read_hires_angle()?,
+                                   r.read_hires_angle()?])
+            }
+            svc::SERVERINFO => {
+                let info = server_info(r)?;
+                self.max_clients = info.max_clients;
+                self.game = Game::from_game_dir(info.game_dir);
+                Message::ServerInfo(info)
+            }
+            svc::LIGHTSTYLE => {
+                Message::LightStyle(LightStyle { index: r.read_u8()?,
+                                                 style: r.read_string()? })
+            }
+            svc::UPDATEUSERINFO => {
+                Message::UpdateUserInfo(UpdateUserInfo { client_index: r.read_u8()?,
+                                                         user_id: r.read_i32()?,
+                                                         info: r.read_string()?,
+                                                         cd_key_hash: r.read_bytes(16)? })
+            }
+            svc::DELTADESCRIPTION => {
+                let description = self.delta_description_message(r)?;
+                self.delta_descriptions
+                    .insert(description.name.clone(), description.clone());
+                Message::DeltaDescription(description)
+            }
+            svc::CLIENTDATA => Message::ClientData(self.client_data(r)?),
+            svc::STOPSOUND => Message::StopSound(r.read_i16()?),
+            svc::PINGS => Message::Pings(pings(r)?),
+            svc::PARTICLE => {
+                Message::Particle(Particle { origin: coord3(r)?,
+                                            direction: [f32::from(r.read_i8()?) / 16f32,
+                                                        f32::from(r.read_i8()?) / 16f32,
+                                                        f32::from(r.read_i8()?) / 16f32],
+                                            count: r.read_u8()?,
+                                            color: r.read_u8()? })
+            }
+            svc::SPAWNSTATIC => Message::SpawnStatic(spawn_static(r)?),
+            svc::EVENT_RELIABLE => Message::EventReliable(self.event_reliable(r)?),
+            svc::SPAWNBASELINE => {
+                let baseline = self.spawn_baseline(r)?;
+                self.instanced_baseline_count = baseline.instanced.len();
+                Message::SpawnBaseline(baseline)
+            }
+            svc::TEMPENTITY => Message::TempEntity(temp_entity(r)?),
+            svc::SETPAUSE => Message::SetPause(r.read_u8()? != 0),
+            svc::SIGNONNUM => Message::SignOnNum(r.read_u8()?),
+            svc::CENTERPRINT => Message::CenterPrint(r.read_string()?),
+            svc::KILLEDMONSTER => Message::KilledMonster,
+            svc::FOUNDSECRET => Message::FoundSecret,
+            svc::SPAWNSTATICSOUND => {
+                Message::SpawnStaticSound(SpawnStaticSound { origin: coord3(r)?,
+                                                             sound_index: r.read_u16()?,
+                                                             volume: f32::from(r.read_u8()?)
+                                                                     / 255f32,
+                                                             attenuation: f32::from(r.read_u8()?)
+                                                                          / 64f32,
+                                                             entity: r.read_u16()?,
+                                                             pitch: r.read_u8()?,
+                                                             flags: r.read_u8()? })
+            }
+            svc::INTERMISSION => Message::Intermission,
+            svc::FINALE => Message::Finale(r.read_string()?),
+            svc::CDTRACK => {
+                Message::CdTrack(CdTrack { track: r.read_u8()?,
+                                           loop_track: r.read_u8()? })
+            }
+            svc::RESTORE => {
+                let save_name = r.read_string()?;
+                let count = r.read_u8()?;
+                let map_names = (0..count).map(|_| r.read_string())
+                                          .collect::<Result<_, _>>()?;
+                Message::Restore(Restore { save_name,
+                                           map_names })
+            }
+            svc::CUTSCENE => Message::Cutscene(r.read_string()?),
+            svc::WEAPONANIM => {
+                Message::WeaponAnim(WeaponAnim { sequence: r.read_u8()?,
+                                                 body: r.read_u8()? })
+            }
+            svc::DECALNAME => {
+                Message::DecalName(DecalName { index: r.read_u8()?,
+                                               name: r.read_string()? })
+            }
+            svc::ROOMTYPE => Message::RoomType(r.read_i16()?),
+            svc::ADDANGLE => Message::AddAngle(r.read_hires_angle()?),
+            svc::NEWUSERMSG => {
+                let index = r.read_u8()?;
+                let size = match r.read_u8()? {
+                    255 => None,
+                    x => Some(x),
+                };
+                let name = r.read_bytes(16)?;
+                let name = &name[..name.iter().position(|&c| c == 0).unwrap_or(16)];
+
+                self.user_messages
+                    .register(UserMessageInfo { index,
+                                                size,
+                                                name: String::from_utf8_lossy(name).into_owned() });
+                Message::NewUserMsg(NewUserMsg { index, size, name })
+            }
+            svc::PACKETENTITIES => Message::PacketEntities(self.packet_entities(r)?),
+            svc::DELTAPACKETENTITIES => {
+                Message::DeltaPacketEntities(self.delta_packet_entities(r)?)
+            }
+            svc::CHOKE => Message::Choke,
+            svc::RESOURCELIST => Message::ResourceList(resource_list(r)?),
+            svc::NEWMOVEVARS => Message::NewMoveVars(new_move_vars(r)?),
+            svc::RESOURCEREQUEST => {
+                Message::ResourceRequest(ResourceRequest { spawn_count: r.read_i32()?,
+                                                           unknown: r.read_i32()? })
+            }
+            svc::CUSTOMIZATION => Message::Customization(customization(r)?),
+            svc::CROSSHAIRANGLE => {
+                Message::CrosshairAngle(CrosshairAngle { pitch: f32::from(r.read_i8()?) / 5f32,
+                                                         yaw: f32::from(r.read_i8()?) / 5f32 })
+            }
+            svc::SOUNDFADE => {
+                Message::SoundFade(SoundFade { initial_percent: r.read_u8()?,
+                                               hold_time: r.read_u8()?,
+                                               fade_out_time: r.read_u8()?,
+                                               fade_in_time: r.read_u8()? })
+            }
+            svc::FILETXFERFAILED => Message::FileTxferFailed(r.read_string()?),
+            svc::HLTV => {
+                let mode = r.read_u8()?;
+                // HLTV_STATUS carries the proxy status.
+                let status = if mode == 1 { Some(r.read_bytes(18)?) } else { None };
+                Message::Hltv(Hltv { mode, status })
+            }
+            svc::DIRECTOR => {
+                let length = r.read_u8()?;
+                Message::Director(r.read_bytes(length as usize)?)
+            }
+            svc::VOICEINIT => {
+                Message::VoiceInit(VoiceInit { codec_name: r.read_string()?,
+                                               quality: r.read_u8()? })
+            }
+            svc::VOICEDATA => {
+                let client_index = r.read_u8()?;
+                let length = r.read_u16()?;
+                Message::VoiceData(VoiceData { client_index,
+                                               data: r.read_bytes(length as usize)? })
+            }
+            svc::SENDEXTRAINFO => {
+                Message::SendExtraInfo(SendExtraInfo { fallback_dir: r.read_string()?,
+                                                       can_cheat: r.read_u8()? })
+            }
+            svc::TIMESCALE => Message::TimeScale(r.read_f32()?),
+            svc::RESOURCELOCATION => Message::ResourceLocation(r.read_string()?),
+            svc::SENDCVARVALUE => Message::SendCvarValue(r.read_string()?),
+            svc::SENDCVARVALUE2 => {
+                Message::SendCvarValue2(SendCvarValue2 { request_id: r.read_i32()?,
+                                                         name: r.read_string()? })
+            }
+            x if x >= FIRST_USER_MESSAGE => Message::UserMessage(self.user_message(r, x)?),
+            x => return Err(Error::InvalidMessage(x)),
+        };
+
+        Ok(message)
+    }
+
+    fn user_message<'a>(&self, r: &mut BitReader<'a>, index: u8) -> Result<UserMessage<'a>, Error> {
+        let info = self.user_messages
+                       .get(index)
+                       .ok_or(Error::UnknownUserMessage(index))?;
+        let size = match info.size {
+            Some(size) => size,
+            None => r.read_u8()?,
+        };
+        let data = r.read_bytes(size as usize)?;
+
+        // Fall back to the raw data if the message doesn't match the expected layout.
+        let typed = user_messages::decode(&info.name, data, self.game)
+            .unwrap_or(TypedUserMessage::Raw);
+
+        Ok(UserMessage { index, data, typed })
+    }
+
+    fn event(&self, r: &mut BitReader) -> Result<Vec<EventInfo>, Error> {
+        let count = r.read_bits(5)?;
+        let mut events = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            let index = r.read_bits(10)? as u16;
+
+            let mut packet_index = None;
+            let mut args = None;
+            if r.read_bit()? {
+                packet_index = Some(r.read_bits(11)? as u16);
+
+                if r.read_bit()? {
+                    args = Some(self.read_delta(r, "event_t")?);
+                }
+            }
+
+            let fire_time = if r.read_bit()? {
+                Some(r.read_bits(16)? as f32 / 100f32)
+            } else {
+                None
+            };
+
+            events.push(EventInfo { index,
+                                    packet_index,
+                                    args,
+                                    fire_time });
+        }
+
+        r.align();
+        Ok(events)
+    }
+
+    fn event_reliable(&self, r: &mut BitReader) -> Result<EventReliable, Error> {
+        let index = r.read_bits(10)? as u16;
+        let args = self.read_delta(r, "event_t")?;
+        let delay = if r.read_bit()? {
+            Some(r.read_bits(16)? as f32 / 100f32)
+        } else {
+            None
+        };
+
+        r.align();
+        Ok(EventReliable { index, args, delay })
+    }
+
+    fn delta_description_message(&self, r: &mut BitReader) -> Result<DeltaDescription, Error> {
+        let name = String::from_utf8_lossy(r.read_string()?).into_owned();
+        let field_count = r.read_u16()?;
+
+        let description = self.description(META_DESCRIPTION_NAME)?
+                              .read_description(r, name, field_count as usize)?;
+
+        r.align();
+        Ok(description)
+    }
+
+    fn client_data(&self, r: &mut BitReader) -> Result<ClientData, Error> {
+        let delta_sequence = if r.read_bit()? {
+            Some(r.read_bits(8)? as u8)
+        } else {
+            None
+        };
+
+        let client_data = self.read_delta(r, "clientdata_t")?;
+
+        let mut weapons = Vec::new();
+        while r.read_bit()? {
+            let index = r.read_bits(6)? as u8;
+            let data = self.read_delta(r, "weapon_data_t")?;
+            weapons.push(WeaponData { index, data });
+        }
+
+        r.align();
+        Ok(ClientData { delta_sequence,
+                        client_data,
+                        weapons })
+    }
+
+    fn spawn_baseline(&self, r: &mut BitReader) -> Result<SpawnBaseline, Error> {
+        let mut entities = Vec::new();
+        loop {
+            let index = r.read_bits(11)? as u16;
+            if index == (1 << 11) - 1 {
+                break;
+            }
+
+            let entity_type = r.read_bits(2)? as u8;
+            let custom = entity_type & ENTITY_BEAM != 0;
+            let state = self.read_delta(r, self.entity_description(index, custom))?;
+
+            entities.push(BaselineEntity { index,
+                                           entity_type,
+                                           state });
+        }
+
+        if r.read_bits(5)? != (1 << 5) - 1 {
+            return Err(Error::InvalidSpawnBaseline);
+        }
+
+        let instanced_count = r.read_bits(6)?;
+        let instanced = (0..instanced_count).map(|_| self.read_delta(r, "entity_state_t"))
+                                            .collect::<Result<_, _>>()?;
+
+        r.align();
+        Ok(SpawnBaseline { entities,
+                           instanced })
+    }
+
+    /// Reads the entity index, which is stored relative to the previous one.
+    fn entity_index(r: &mut BitReader, previous: u16) -> Result<u16, Error> {
+        if r.read_bit()? {
+            previous.checked_add(1).ok_or(Error::InvalidEntityIndex)
+        } else if r.read_bit()? {
+            Ok(r.read_bits(11)? as u16)
+        } else {
+            previous.checked_add(r.read_bits(6)? as u16)
+                    .ok_or(Error::InvalidEntityIndex)
+        }
+    }
+
+    fn entity_delta(&self,
+                    r: &mut BitReader,
+                    index: u16,
+                    full: bool)
+                    -> Result<EntityDelta, Error> {
+        let custom = r.read_bit()?;
+
+        let mut instanced_baseline = None;
+        if self.instanced_baseline_count > 0 && r.read_bit()? {
+            instanced_baseline = Some(r.read_bits(6)? as u8);
+        }
+
+        let mut baseline_offset = None;
+        if full && instanced_baseline.is_none() && r.read_bit()? {
+            baseline_offset = Some(r.read_bits(6)? as u8);
+        }
+
+        let state = self.read_delta(r, self.entity_description(index, custom))?;
+
+        Ok(EntityDelta { index,
+                         remove: false,
+                         custom,
+                         instanced_baseline,
+                         baseline_offset,
+                         state })
+    }
+
+    fn packet_entities(&self, r: &mut BitReader) -> Result<PacketEntities, Error> {
+        let entity_count = r.read_bits(16)? as u16;
+
+        let mut entities = Vec::new();
+        let mut index = 0u16;
+        while r.peek_bits(16)? != 0 {
+            index = Self::entity_index(r, index)?;
+            entities.push(self.entity_delta(r, index, true)?);
+        }
+        r.read_bits(16)?;
+
+        r.align();
+        Ok(PacketEntities { entity_count,
+                            entities })
+    }
+
+    fn delta_packet_entities(&self, r: &mut BitReader) -> Result<DeltaPacketEntities, Error> {
+        let entity_count = r.read_bits(16)? as u16;
+        let delta_sequence = r.read_bits(8)? as u8;
+
+        let mut entities = Vec::new();
+        let mut index = 0u16;
+        while r.peek_bits(16)? != 0 {
+            let remove = r.read_bit()?;
+            index = if r.read_bit()? {
+                r.read_bits(11)? as u16
+            } else {
+                index.checked_add(r.read_bits(6)? as u16)
+                     .ok_or(Error::InvalidEntityIndex)?
+            };
+
+            if remove {
+                entities.push(EntityDelta { index,
+                                            remove,
+                                            custom: false,
+                                            instanced_baseline: None,
+                                            baseline_offset: None,
+                                            state: Delta::new() });
+            } else {
+                entities.push(self.entity_delta(r, index, false)?);
+            }
+        }
+        r.read_bits(16)?;
+
+        r.align();
+        Ok(DeltaPacketEntities { entity_count,
+                                 delta_sequence,
+                                 entities })
+    }
+}
+
+fn sound(r: &mut BitReader) -> Result<Sound, Error> {
+    let flags = r.read_bits(9)? as u16;
+
+    let volume = if flags & SND_VOLUME != 0 {
+        r.read_bits(8)? as f32 / 255f32
+    } else {
+        1f32
+    };
+    let attenuation = if flags & SND_ATTENUATION != 0 {
+        r.read_bits(8)? as f32 / 64f32
+    } else {
+        0.8f32
+    };
+
+    let channel = r.read_bits(3)? as u8;
+    let entity = r.read_bits(11)? as u16;
+    let sound_index = r.read_bits(if flags & SND_LARGE_INDEX != 0 { 16 } else { 8 })? as u16;
+    let origin = r.read_bit_vec3_coord()?;
+    let pitch = if flags & SND_PITCH != 0 {
+        r.read_bits(8)? as u8
+    } else {
+        100
+    };
+
+    r.align();
+    Ok(Sound { flags,
+               volume,
+               attenuation,
+               channel,
+               entity,
+               sound_index,
+               origin,
+               pitch })
+}
+
+fn server_info<'a>(r: &mut BitReader<'a>) -> Result<ServerInfo<'a>, Error> {
+    let info = ServerInfo { protocol: r.read_i32()?,
+                            spawn_count: r.read_i32()?,
+                            map_crc: r.read_i32()?,
+                            client_dll_hash: r.read_bytes(16)?,
+                            max_clients: r.read_u8()?,
+                            player_index: r.read_u8()?,
+                            deathmatch: r.read_u8()?,
+                            game_dir: r.read_string()?,
+                            hostname: r.read_string()?,
+                            map_file_name: r.read_string()?,
+                            map_cycle: r.read_string()? };
+
+    // Always zero.
+    r.read_u8()?;
+
+    Ok(info)
+}
+
+fn pings(r: &mut BitReader) -> Result<Vec<Ping>, Error> {
+    let mut pings = Vec::new();
+    while r.read_bit()? {
+        pings.push(Ping { client_index: r.read_bits(5)? as u8,
+                          ping: r.read_bits(12)? as u16,
+                          loss: r.read_bits(7)? as u8 });
+    }
+
+    r.align();
+    Ok(pings)
+}
+
+fn spawn_static(r: &mut BitReader) -> Result<SpawnStatic, Error> {
+    let model_index = r.read_i16()?;
+    let sequence = r.read_u8()?;
+    let frame = r.read_u8()?;
+    let color_map = r.read_i16()?;
+    let skin = r.read_u8()?;
+
+    let mut origin = [0f32; 3];
+    let mut angles = [0f32; 3];
+    for i in 0..3 {
+        origin[i] = r.read_coord()?;
+        angles[i] = r.read_angle()?;
+    }
+
+    let render_mode = r.read_u8()?;
+    let (render_amount, render_color, render_fx) = if render_mode != 0 {
+        (r.read_u8()?, [r.read_u8()?, r.read_u8()?, r.read_u8()?], r.read_u8()?)
+    } else {
+        (0, [0; 3], 0)
+    };
+
+    Ok(SpawnStatic { model_index,
+                     sequence,
+                     frame,
+                     color_map,
+                     skin,
+                     origin,
+                     angles,
+                     render_mode,
+                     render_amount,
+                     render_color,
+                     render_fx })
+}
+
+fn temp_entity<'a>(r: &mut BitReader<'a>) -> Result<TempEntity<'a>, Error> {
+    let entity_type = r.read_u8()?;
+
+    let data = match entity_type {
+        // TE_BSPDECAL: the model index is present only for non-world entities.
+        13 => {
+            let start = r.clone();
+            r.read_bytes(8)?;
+            let size = if r.read_u16()? != 0 { 12 } else { 10 };
+            *r = start;
+            r.read_bytes(size)?
+        }
+        // TE_TEXTMESSAGE: the effect time is present only for effect 2, followed by the text.
+        29 => {
+            let start = r.clone();
+            r.read_bytes(5)?;
+            let effect = r.read_u8()?;
+            r.read_bytes(if effect == 2 { 16 } else { 14 })?;
+            let text = r.read_string()?;
+
+            let mut size = 20 + text.len() + 1;
+            if effect == 2 {
+                size += 2;
+            }
+            *r = start;
+            r.read_bytes(size)?
+        }
+        x => {
+            let size = temp_entity_size(x).ok_or(Error::UnknownTempEntity(x))?;
+            r.read_bytes(size)?
+        }
+    };
+
+    Ok(TempEntity { entity_type,
+                    data })
+}
+
+fn resource_list(r: &mut BitReader) -> Result<ResourceList, Error> {
+    let count = r.read_bits(12)?;
+    let mut resources = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        let resource_type = r.read_bits(4)? as u8;
+        let name = r.read_bit_string()?;
+        let index = r.read_bits(12)? as u16;
+        let download_size = r.read_bits(24)?;
+        let flags = r.read_bits(3)? as u8;
+
+        // RES_CUSTOM
+        let md5_hash = if flags & 4 != 0 {
+            Some(r.read_bit_bytes(16)?)
+        } else {
+            None
+        };
+        let reserved = if r.read_bit()? {
+            Some(r.read_bit_bytes(32)?)
+        } else {
+            None
+        };
+
+        resources.push(Resource { resource_type,
+                                  name,
+                                  index,
+                                  download_size,
+                                  flags,
+                                  md5_hash,
+                                  reserved });
+    }
+
+    let mut consistency = Vec::new();
+    if r.read_bit()? {
+        let mut last = 0u16;
+        while r.read_bit()? {
+            let index = if r.read_bit()? {
+                last.checked_add(r.read_bits(5)? as u16)
+                    .ok_or(Error::InvalidConsistencyIndex)?
+            } else {
+                r.read_bits(10)? as u16
+            };
+
+            consistency.push(index);
+            last = index;
+        }
+    }
+
+    r.align();
+    Ok(ResourceList { resources,
+                      consistency })
+}
+
+fn new_move_vars<'a>(r: &mut BitReader<'a>) -> Result<NewMoveVars<'a>, Error> {
+    Ok(NewMoveVars { gravity: r.read_f32()?,
+                     stopspeed: r.read_f32()?,
+                     maxspeed: r.read_f32()?,
+                     spectatormaxspeed: r.read_f32()?,
+                     accelerate: r.read_f32()?,
+                     airaccelerate: r.read_f32()?,
+                     wateraccelerate: r.read_f32()?,
+                     friction: r.read_f32()?,
+                     edgefriction: r.read_f32()?,
+                     waterfriction: r.read_f32()?,
+                     entgravity: r.read_f32()?,
+                     bounce: r.read_f32()?,
+                     stepsize: r.read_f32()?,
+                     maxvelocity: r.read_f32()?,
+                     zmax: r.read_f32()?,
+                     wave_height: r.read_f32()?,
+                     footsteps: r.read_u8()?,
+                     rollangle: r.read_f32()?,
+                     rollspeed: r.read_f32()?,
+                     skycolor: [r.read_f32()?, r.read_f32()?, r.read_f32()?],
+                     skyvec: [r.read_f32()?, r.read_f32()?, r.read_f32()?],
+                     sky_name: r.read_string()? })
+}
+
+fn customization<'a>(r: &mut BitReader<'a>) -> Result<Customization<'a>, Error> {
+    let client_index = r.read_u8()?;
+    let resource_type = r.read_u8()?;
+    let name = r.read_string()?;
+    let index = r.read_u16()?;
+    let download_size = r.read_i32()?;
+    let flags = r.read_u8()?;
+
+    // RES_CUSTOM
+    let md5_hash = if flags & 4 != 0 {
+        Some(r.read_bytes(16)?)
+    } else {
+        None
+    };
+
+    Ok(Customization { client_index,
+                       resource_type,
+                       name,
+                       index,
+                       download_size,
+                       flags,
+                       md5_hash })
+}
@@ -0,0 +1,208 @@
+//! Delta descriptions and delta decoding.
+//!
+//! The engine sends entity states, client data, weapon data and event arguments as deltas: only
+//! the fields which changed are transmitted, using the layout from a delta description. The
+//! descriptions themselves are sent by the server in `svc_deltadescription` messages.
+
+use std::collections::BTreeMap;
+
+use super::bit_reader::BitReader;
+use super::Error;
+
+pub const DT_BYTE: u32 = 1;
+pub const DT_SHORT: u32 = 1 << 1;
+pub const DT_FLOAT: u32 = 1 << 2;
+pub const DT_INTEGER: u32 = 1 << 3;
+pub const DT_ANGLE: u32 = 1 << 4;
+pub const DT_TIMEWINDOW_8: u32 = 1 << 5;
+pub const DT_TIMEWINDOW_BIG: u32 = 1 << 6;
+pub const DT_STRING: u32 = 1 << 7;
+pub const DT_SIGNED: u32 = 1 << 31;
+
+/// The name of the built-in description used to decode delta descriptions.
+pub const META_DESCRIPTION_NAME: &str = "delta_description_t";
+
+/// A decoded delta field value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeltaValue {
+    Integer(i64),
+    Float(f32),
+    String(Vec<u8>),
+}
+
+/// Decoded delta fields, by field name.
+///
+/// Only the fields which were present in the delta are included.
+pub type Delta = BTreeMap<String, DeltaValue>;
+
+/// A field of a delta description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaField {
+    pub name: String,
+    /// A combination of the `DT_*` flags.
+    pub flags: u32,
+    pub offset: u16,
+    pub size: u8,
+    /// The number of bits the field is transmitted with.
+    pub bits: u8,
+    pub pre_multiplier: f32,
+    pub post_multiplier: f32,
+}
+
+/// A delta description: the layout used to transmit deltas of some structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaDescription {
+    pub name: String,
+    pub fields: Vec<DeltaField>,
+}
+
+impl DeltaValue {
+    /// Returns the value as an integer, converting floats.
+    ///
+    /// Returns `None` for strings.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            DeltaValue::Integer(x) => Some(x),
+            DeltaValue::Float(x) => Some(x as i64),
+            DeltaValue::String(_) => None,
+        }
+    }
+
+    /// Returns the value as a float, converting integers.
+    ///
+    /// Returns `None` for strings.
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            DeltaValue::Integer(x) => Some(x as f32),
+            DeltaValue::Float(x) => Some(x),
+            DeltaValue::String(_) => None,
+        }
+    }
+
+    /// Returns the value as a byte string.
+    ///
+    /// Returns `None` for numbers.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            DeltaValue::String(ref x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl DeltaField {
+    fn meta(name: &str, flags: u32, bits: u8, pre_multiplier: f32) -> Self {
+        DeltaField { name: name.to_string(),
+                     flags,
+                     offset: 0,
+                     size: 0,
+                     bits,
+                     pre_multiplier,
+                     post_multiplier: 1f32 }
+    }
+
+    /// Creates a field from a delta decoded with the meta description.
+    fn from_delta(delta: &Delta) -> Self {
+        let int = |name| delta.get(name).and_then(DeltaValue::as_i64).unwrap_or(0);
+        let float = |name| delta.get(name).and_then(DeltaValue::as_f32).unwrap_or(1f32);
+        let name = delta.get("fieldName")
+                        .and_then(DeltaValue::as_bytes)
+                        .map(|x| String::from_utf8_lossy(x).into_owned())
+                        .unwrap_or_default();
+
+        DeltaField { name,
+                     flags: int("fieldType") as u32,
+                     offset: int("fieldOffset") as u16,
+                     size: int("fieldSize") as u8,
+                     bits: int("significant_bits") as u8,
+                     pre_multiplier: float("premultiply"),
+                     post_multiplier: float("postmultiply") }
+    }
+
+    /// Reads a value of this field.
+    pub fn read(&self, r: &mut BitReader) -> Result<DeltaValue, Error> {
+        let signed = self.flags & DT_SIGNED != 0;
+        let bits = u32::from(self.bits);
+        if self.flags & DT_STRING == 0 && (bits == 0 || bits > 32) {
+            return Err(Error::InvalidDeltaField(self.name.clone()));
+        }
+
+        let read_number = |r: &mut BitReader| -> Result<i64, Error> {
+            if signed {
+                r.read_signed_bits(bits).map(i64::from)
+            } else {
+                r.read_bits(bits).map(i64::from)
+            }
+        };
+
+        let value = match self.flags & !DT_SIGNED {
+            DT_BYTE | DT_SHORT | DT_INTEGER => {
+                let value = read_number(r)?;
+                if self.pre_multiplier == 1f32 || self.pre_multiplier == 0f32 {
+                    DeltaValue::Integer(value)
+                } else {
+                    DeltaValue::Integer((value as f64 / f64::from(self.pre_multiplier)) as i64)
+                }
+            }
+            DT_FLOAT => {
+                let value = read_number(r)? as f32 / self.pre_multiplier;
+                DeltaValue::Float(value * self.post_multiplier)
+            }
+            DT_ANGLE => DeltaValue::Float(r.read_bit_angle(bits)?),
+            DT_TIMEWINDOW_8 => DeltaValue::Float(r.read_signed_bits(8)? as f32 / 100f32),
+            DT_TIMEWINDOW_BIG => {
+                DeltaValue::Float(r.read_signed_bits(bits)? as f32 / self.pre_multiplier)
+            }
+            DT_STRING => DeltaValue::String(r.read_bit_string()?),
+            _ => return Err(Error::InvalidDeltaField(self.name.clone())),
+        };
+
+        Ok(value)
+    }
+}
+
+impl DeltaDescription {
+    /// Returns the built-in description used to decode delta descriptions.
+    pub fn meta() -> Self {
+        DeltaDescription { name: META_DESCRIPTION_NAME.to_string(),
+                           fields: vec![DeltaField::meta("fieldType", DT_INTEGER, 32, 1f32),
+                                        DeltaField::meta("fieldName", DT_STRING, 1, 1f32),
+                                        DeltaField::meta("fieldOffset", DT_INTEGER, 16, 1f32),
+                                        DeltaField::meta("fieldSize", DT_INTEGER, 8, 1f32),
+                                        DeltaField::meta("significant_bits", DT_INTEGER, 8, 1f32),
+                                        DeltaField::meta("premultiply", DT_FLOAT, 32, 4000f32),
+                                        DeltaField::meta("postmultiply", DT_FLOAT, 32, 4000f32),] }
+    }
+
+    /// Reads a delta using this description.
+    pub fn read(&self, r: &mut BitReader) -> Result<Delta, Error> {
+        let mask_len = r.read_bits(3)? as usize;
+        let mut mask = [0u8; 8];
+        for byte in &mut mask[..mask_len] {
+            *byte = r.read_bits(8)? as u8;
+        }
+
+        let mut delta = Delta::new();
+        for (i, field) in self.fields.iter().enumerate().take(mask_len * 8) {
+            if mask[i / 8] & (1 << (i % 8)) != 0 {
+                delta.insert(field.name.clone(), field.read(r)?);
+            }
+        }
+
+        Ok(delta)
+    }
+
+    /// Reads the fields of a delta description.
+    ///
+    /// `self` must be the meta description.
+    pub(crate) fn read_description(&self,
+                                   r: &mut BitReader,
+                                   name: String,
+                                   field_count: usize)
+                                   -> Result<DeltaDescription, Error> {
+        let fields = (0..field_count).map(|_| self.read(r).map(|d| DeltaField::from_delta(&d)))
+                                     .collect::<Result<_, _>>()?;
+
+        Ok(DeltaDescription { name, fields })
+    }
+}
@@ -0,0 +1,389 @@
+//! Types for engine network messages.
+//!
+//! Strings are byte strings without the null terminator.
+
+use super::delta::{Delta, DeltaDescription};
+use super::user_messages::TypedUserMessage;
+
+/// An engine network message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message<'a> {
+    Nop,
+    Disconnect(&'a [u8]),
+    Event(Vec<EventInfo>),
+    Version(i32),
+    SetView(i16),
+    Sound(Sound),
+    Time(f32),
+    Print(&'a [u8]),
+    StuffText(&'a [u8]),
+    SetAngle([f32; 3]),
+    ServerInfo(ServerInfo<'a>),
+    LightStyle(LightStyle<'a>),
+    UpdateUserInfo(UpdateUserInfo<'a>),
+    DeltaDescription(DeltaDescription),
+    ClientData(ClientData),
+    StopSound(i16),
+    Pings(Vec<Ping>),
+    Particle(Particle),
+    SpawnStatic(SpawnStatic),
+    EventReliable(EventReliable),
+    SpawnBaseline(SpawnBaseline),
+    TempEntity(TempEntity<'a>),
+    SetPause(bool),
+    SignOnNum(u8),
+    CenterPrint(&'a [u8]),
+    KilledMonster,
+    FoundSecret,
+    SpawnStaticSound(SpawnStaticSound),
+    Intermission,
+    Finale(&'a [u8]),
+    CdTrack(CdTrack),
+    Restore(Restore<'a>),
+    Cutscene(&'a [u8]),
+    WeaponAnim(WeaponAnim),
+    DecalName(DecalName<'a>),
+    RoomType(i16),
+    AddAngle(f32),
+    NewUserMsg(NewUserMsg<'a>),
+    PacketEntities(PacketEntities),
+    DeltaPacketEntities(DeltaPacketEntities),
+    Choke,
+    ResourceList(ResourceList),
+    NewMoveVars(NewMoveVars<'a>),
+    ResourceRequest(ResourceRequest),
+    Customization(Customization<'a>),
+    CrosshairAngle(CrosshairAngle),
+    SoundFade(SoundFade),
+    FileTxferFailed(&'a [u8]),
+    Hltv(Hltv<'a>),
+    Director(&'a [u8]),
+    VoiceInit(VoiceInit<'a>),
+    VoiceData(VoiceData<'a>),
+    SendExtraInfo(SendExtraInfo<'a>),
+    TimeScale(f32),
+    ResourceLocation(&'a [u8]),
+    SendCvarValue(&'a [u8]),
+    SendCvarValue2(SendCvarValue2<'a>),
+    /// A message registered with `svc_newusermsg`.
+    UserMessage(UserMessage<'a>),
+}
+
+/// An event from `svc_event`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventInfo {
+    pub index: u16,
+    pub packet_index: Option<u16>,
+    /// Event arguments, decoded with the `event_t` description.
+    pub args: Option<Delta>,
+    pub fire_time: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    /// A combination of the `SND_*` flags.
+    pub flags: u16,
+    pub volume: f32,
+    pub attenuation: f32,
+    pub channel: u8,
+    pub entity: u16,
+    pub sound_index: u16,
+    pub origin: [f32; 3],
+    pub pitch: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo<'a> {
+    pub protocol: i32,
+    pub spawn_count: i32,
+    pub map_crc: i32,
+    pub client_dll_hash: &'a [u8],
+    pub max_clients: u8,
+    pub player_index: u8,
+    pub deathmatch: u8,
+    pub game_dir: &'a [u8],
+    pub hostname: &'a [u8],
+    pub map_file_name: &'a [u8],
+    pub map_cycle: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightStyle<'a> {
+    pub index: u8,
+    pub style: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateUserInfo<'a> {
+    pub client_index: u8,
+    pub user_id: i32,
+    pub info: &'a [u8],
+    pub cd_key_hash: &'a [u8],
+}
+
+/// Client data from `svc_clientdata`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientData {
+    /// The sequence of the frame the delta is based on, if any.
+    pub delta_sequence: Option<u8>,
+    /// Decoded with the `clientdata_t` description.
+    pub client_data: Delta,
+    pub weapons: Vec<WeaponData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeaponData {
+    pub index: u8,
+    /// Decoded with the `weapon_data_t` description.
+    pub data: Delta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ping {
+    pub client_index: u8,
+    pub ping: u16,
+    pub loss: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+    pub count: u8,
+    pub color: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnStatic {
+    pub model_index: i16,
+    pub sequence: u8,
+    pub frame: u8,
+    pub color_map: i16,
+    pub skin: u8,
+    pub origin: [f32; 3],
+    pub angles: [f32; 3],
+    pub render_mode: u8,
+    pub render_amount: u8,
+    pub render_color: [u8; 3],
+    pub render_fx: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventReliable {
+    pub index: u16,
+    /// Decoded with the `event_t` description.
+    pub args: Delta,
+    pub delay: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnBaseline {
+    pub entities: Vec<BaselineEntity>,
+    /// Instanced baselines, decoded with the `entity_state_t` description.
+    pub instanced: Vec<Delta>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaselineEntity {
+    pub index: u16,
+    /// A combination of the `ENTITY_*` flags.
+    pub entity_type: u8,
+    pub state: Delta,
+}
+
+/// A temporary entity.
+///
+/// The data layout depends on the entity type, one of the `TE_*` values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TempEntity<'a> {
+    pub entity_type: u8,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnStaticSound {
+    pub origin: [f32; 3],
+    pub sound_index: u16,
+    pub volume: f32,
+    pub attenuation: f32,
+    pub entity: u16,
+    pub pitch: u8,
+    pub flags: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CdTrack {
+    pub track: u8,
+    pub loop_track: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restore<'a> {
+    pub save_name: &'a [u8],
+    pub map_names: Vec<&'a [u8]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeaponAnim {
+    pub sequence: u8,
+    pub body: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecalName<'a> {
+    pub index: u8,
+    pub name: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewUserMsg<'a> {
+    pub index: u8,
+    /// The message size, `None` if the size is variable.
+    pub size: Option<u8>,
+    pub name: &'a [u8],
+}
+
+/// Entity states from `svc_packetentities`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketEntities {
+    pub entity_count: u16,
+    pub entities: Vec<EntityDelta>,
+}
+
+/// Entity states from `svc_deltapacketentities`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaPacketEntities {
+    pub entity_count: u16,
+    /// The sequence of the frame the entity states are based on.
+    pub delta_sequence: u8,
+    pub entities: Vec<EntityDelta>,
+}
+
+/// An entity state delta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityDelta {
+    pub index: u16,
+    /// If `true`, the entity is removed and `state` is empty.
+    pub remove: bool,
+    pub custom: bool,
+    /// The instanced baseline the delta is based on, if any.
+    pub instanced_baseline: Option<u8>,
+    /// The delta is based on the entity this many entries back in the same packet, if any.
+    pub baseline_offset: Option<u8>,
+    /// Decoded with `entity_state_t`, `entity_state_player_t` or `custom_entity_state_t`.
+    pub state: Delta,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceList {
+    pub resources: Vec<Resource>,
+    pub consistency: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    pub resource_type: u8,
+    pub name: Vec<u8>,
+    pub index: u16,
+    pub download_size: u32,
+    pub flags: u8,
+    pub md5_hash: Option<Vec<u8>>,
+    pub reserved: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewMoveVars<'a> {
+    pub gravity: f32,
+    pub stopspeed: f32,
+    pub maxspeed: f32,
+    pub spectatormaxspeed: f32,
+    pub accelerate: f32,
+    pub airaccelerate: f32,
+    pub wateraccelerate: f32,
+    pub friction: f32,
+    pub edgefriction: f32,
+    pub waterfriction: f32,
+    pub entgravity: f32,
+    pub bounce: f32,
+    pub stepsize: f32,
+    pub maxvelocity: f32,
+    pub zmax: f32,
+    pub wave_height: f32,
+    pub footsteps: u8,
+    pub rollangle: f32,
+    pub rollspeed: f32,
+    pub skycolor: [f32; 3],
+    pub skyvec: [f32; 3],
+    pub sky_name: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceRequest {
+    pub spawn_count: i32,
+    pub unknown: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Customization<'a> {
+    pub client_index: u8,
+    pub resource_type: u8,
+    pub name: &'a [u8],
+    pub index: u16,
+    pub download_size: i32,
+    pub flags: u8,
+    pub md5_hash: Option<&'a [u8]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrosshairAngle {
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundFade {
+    pub initial_percent: u8,
+    pub hold_time: u8,
+    pub fade_out_time: u8,
+    pub fade_in_time: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hltv<'a> {
+    pub mode: u8,
+    /// Additional data for `HLTV_STATUS`.
+    pub status: Option<&'a [u8]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceInit<'a> {
+    pub codec_name: &'a [u8],
+    pub quality: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceData<'a> {
+    pub client_index: u8,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendExtraInfo<'a> {
+    pub fallback_dir: &'a [u8],
+    pub can_cheat: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendCvarValue2<'a> {
+    pub request_id: i32,
+    pub name: &'a [u8],
+}
+
+/// A user message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserMessage<'a> {
+    pub index: u8,
+    pub data: &'a [u8],
+    /// The message decoded according to its registered name.
+    pub typed: TypedUserMessage<'a>,
+}
@@ -0,0 +1,264 @@
+//! Decoding of the engine network messages stored in `NetMsgData::msg`.
+//!
+//! A NetMsg frame contains the network messages the client received from the server in that frame.
+//! Some messages depend on state set up by earlier messages (delta descriptions, registered user
+//! messages, the maximum number of clients), so messages must be decoded with the same
+//! `MessageDecoder` in the order they appear in the demo, starting from the first frame.
+//!
+//! Entity states, client data and weapon data are sent as deltas. `StateTracker` decodes the
+//! messages and applies the deltas to keep track of the full states after every frame.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! use hldemo::netmsg::{Message, MessageDecoder};
+//!
+//! let mut bytes = Vec::new();
+//! let mut f = File::open("demo.dem")?;
+//! f.read_to_end(&mut bytes);
+//!
+//! let demo = hldemo::Demo::parse(&bytes)?;
+//! let mut decoder = MessageDecoder::new(demo.header.net_protocol)?;
+//!
+//! for entry in &demo.directory.entries {
+//!     for frame in &entry.frames {
+//!         if let hldemo::FrameData::NetMsg((_, ref data)) = frame.data {
+//!             for message in decoder.decode(&data.msg)? {
+//!                 if let Message::Print(text) = message {
+//!                     println!("{}", String::from_utf8_lossy(text));
+//!                 }
+//!             }
+//!         }
+//!     }
+//! }
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+pub mod bit_reader;
+mod decode;
+pub mod delta;
+pub mod messages;
+pub mod state;
+pub mod user_messages;
+
+pub use self::bit_reader::BitReader;
+pub use self::delta::{Delta, DeltaDescription, DeltaField, DeltaValue};
+pub use self::messages::*;
+pub use self::state::{EntityState, Snapshot, StateTracker};
+pub use self::user_messages::{Game, TypedUserMessage, UserMessageInfo, UserMessageRegistry};
+
+/// The network protocol supported by the decoder.
+pub const SUPPORTED_NET_PROTOCOL: i32 = 48;
+
+/// The first message index used for user messages.
+pub const FIRST_USER_MESSAGE: u8 = 64;
+
+/// Engine message indices.
+pub mod svc {
+    pub const BAD: u8 = 0;
+    pub const NOP: u8 = 1;
+    pub const DISCONNECT: u8 = 2;
+    pub const EVENT: u8 = 3;
+    pub const VERSION: u8 = 4;
+    pub const SETVIEW: u8 = 5;
+    pub const SOUND: u8 = 6;
+    pub const TIME: u8 = 7;
+    pub const PRINT: u8 = 8;
+    pub const STUFFTEXT: u8 = 9;
+    pub const SETANGLE: u8 = 10;
+    pub const SERVERINFO: u8 = 11;
+    pub const LIGHTSTYLE: u8 = 12;
+    pub const UPDATEUSERINFO: u8 = 13;
+    pub const DELTADESCRIPTION: u8 = 14;
+    pub const CLIENTDATA: u8 = 15;
+    pub const STOPSOUND: u8 = 16;
+    pub const PINGS: u8 = 17;
+    pub const PARTICLE: u8 = 18;
+    pub const DAMAGE: u8 = 19;
+    pub const SPAWNSTATIC: u8 = 20;
+    pub const EVENT_RELIABLE: u8 = 21;
+    pub const SPAWNBASELINE: u8 = 22;
+    pub const TEMPENTITY: u8 = 23;
+    pub const SETPAUSE: u8 = 24;
+    pub const SIGNONNUM: u8 = 25;
+    pub const CENTERPRINT: u8 = 26;
+    pub const KILLEDMONSTER: u8 = 27;
+    pub const FOUNDSECRET: u8 = 28;
+    pub const SPAWNSTATICSOUND: u8 = 29;
+    pub const INTERMISSION: u8 = 30;
+    pub const FINALE: u8 = 31;
+    pub const CDTRACK: u8 = 32;
+    pub const RESTORE: u8 = 33;
+    pub const CUTSCENE: u8 = 34;
+    pub const WEAPONANIM: u8 = 35;
+    pub const DECALNAME: u8 = 36;
+    pub const ROOMTYPE: u8 = 37;
+    pub const ADDANGLE: u8 = 38;
+    pub const NEWUSERMSG: u8 = 39;
+    pub const PACKETENTITIES: u8 = 40;
+    pub const DELTAPACKETENTITIES: u8 = 41;
+    pub const CHOKE: u8 = 42;
+    pub const RESOURCELIST: u8 = 43;
+    pub const NEWMOVEVARS: u8 = 44;
+    pub const RESOURCEREQUEST: u8 = 45;
+    pub const CUSTOMIZATION: u8 = 46;
+    pub const CROSSHAIRANGLE: u8 = 47;
+    pub const SOUNDFADE: u8 = 48;
+    pub const FILETXFERFAILED: u8 = 49;
+    pub const HLTV: u8 = 50;
+    pub const DIRECTOR: u8 = 51;
+    pub const VOICEINIT: u8 = 52;
+    pub const VOICEDATA: u8 = 53;
+    pub const SENDEXTRAINFO: u8 = 54;
+    pub const TIMESCALE: u8 = 55;
+    pub const RESOURCELOCATION: u8 = 56;
+    pub const SENDCVARVALUE: u8 = 57;
+    pub const SENDCVARVALUE2: u8 = 58;
+}
+
+pub const SND_VOLUME: u16 = 1;
+pub const SND_ATTENUATION: u16 = 1 << 1;
+pub const SND_LARGE_INDEX: u16 = 1 << 2;
+pub const SND_PITCH: u16 = 1 << 3;
+pub const SND_SENTENCE: u16 = 1 << 4;
+pub const SND_STOP: u16 = 1 << 5;
+pub const SND_CHANGE_VOL: u16 = 1 << 6;
+pub const SND_CHANGE_PITCH: u16 = 1 << 7;
+pub const SND_SPAWNING: u16 = 1 << 8;
+
+pub const ENTITY_NORMAL: u8 = 1;
+pub const ENTITY_BEAM: u8 = 1 << 1;
+
+quick_error! {
+    /// This type represents possible errors that can occur when decoding network messages.
+    #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+    pub enum Error {
+        UnsupportedNetProtocol(protocol: i32) {
+            display("unsupported net protocol: {} (only protocol {} is supported)",
+                    protocol,
+                    SUPPORTED_NET_PROTOCOL)
+        }
+        UnexpectedEnd {
+            display("unexpected end of the message buffer")
+        }
+        InvalidBitCount(count: u32) {
+            display("invalid bit count: {}", count)
+        }
+        InvalidMessage(index: u8) {
+            display("invalid message: {}", index)
+        }
+        UnknownUserMessage(index: u8) {
+            display("unknown user message: {}", index)
+        }
+        UnknownDeltaDescription(name: String) {
+            display("unknown delta description: {}", name)
+        }
+        InvalidDeltaField(name: String) {
+            display("invalid delta field: {}", name)
+        }
+        UnknownTempEntity(entity_type: u8) {
+            display("unknown temporary entity type: {}", entity_type)
+        }
+        InvalidSpawnBaseline {
+            display("invalid spawn baseline footer")
+        }
+        InvalidDeltaSequence(sequence: u8) {
+            display("delta against a frame which wasn't received: {}", sequence)
+        }
+        InvalidConsistencyIndex {
+            display("resource consistency index out of range")
+        }
+        InvalidEntityIndex {
+            display("entity index out of range")
+        }
+    }
+}
+
+/// A decoder for engine network messages.
+///
+/// See the module documentation for details.
+#[derive(Debug, Clone)]
+pub struct MessageDecoder {
+    delta_descriptions: HashMap<String, DeltaDescription>,
+    user_messages: UserMessageRegistry,
+    game: Game,
+    max_clients: u8,
+    instanced_baseline_count: usize,
+}
+
+impl MessageDecoder {
+    /// Creates a decoder for the given network protocol, as found in `Header::net_protocol`.
+    pub fn new(net_protocol: i32) -> Result<Self, Error> {
+        if net_protocol != SUPPORTED_NET_PROTOCOL {
+            return Err(Error::UnsupportedNetProtocol(net_protocol));
+        }
+
+        let meta = DeltaDescription::meta();
+        let mut delta_descriptions = HashMap::new();
+        delta_descriptions.insert(meta.name.clone(), meta);
+
+        Ok(MessageDecoder { delta_descriptions,
+                            user_messages: UserMessageRegistry::new(),
+                            game: Game::HalfLife,
+                            max_clients: 0,
+                            instanced_baseline_count: 0 })
+    }
+
+    /// Decodes all messages in the buffer.
+    pub fn decode<'a>(&mut self, data: &'a [u8]) -> Result<Vec<Message<'a>>, Error> {
+        let mut r = BitReader::new(data);
+        let mut messages = Vec::new();
+
+        while !r.is_empty() {
+            let message = self.message(&mut r)?;
+            messages.push(message);
+        }
+
+        Ok(messages)
+    }
+
+    /// Returns the delta description with the given name, if it was received.
+    pub fn delta_description(&self, name: &str) -> Option<&DeltaDescription> {
+        self.delta_descriptions.get(name)
+    }
+
+    /// Returns the user messages registered so far.
+    #[inline]
+    pub fn user_messages(&self) -> &UserMessageRegistry {
+        &self.user_messages
+    }
+
+    /// Returns the game detected from the last `svc_serverinfo`.
+    #[inline]
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    /// Returns the maximum number of clients from the last `svc_serverinfo`.
+    #[inline]
+    pub fn max_clients(&self) -> u8 {
+        self.max_clients
+    }
+
+    /// Returns `true` if the entity with the given index is a player.
+    #[inline]
+    pub fn is_player(&self, index: u16) -> bool {
+        index >= 1 && index <= u16::from(self.max_clients)
+    }
+}
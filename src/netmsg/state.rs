@@ -0,0 +1,250 @@
+//! Tracking of the full entity, client data and weapon states.
+//!
+//! The server sends entity states, client data and weapon data as deltas against an earlier frame
+//! or against the baselines. `StateTracker` applies these deltas on top of each other to
+//! reconstruct the full state after every NetMsg frame.
+
+use std::collections::BTreeMap;
+
+use types::NetMsgData;
+
+use super::*;
+
+/// The number of frames the client keeps for delta decoding.
+pub const UPDATE_BACKUP: usize = 64;
+
+/// The full state of an entity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntityState {
+    /// `true` if the state was decoded with the `custom_entity_state_t` description (beams).
+    pub custom: bool,
+    /// All fields which were ever sent for the entity. Missing fields are zero.
+    pub fields: Delta,
+}
+
+/// The full state received in a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// Entities in the current packet, by entity index.
+    pub entities: BTreeMap<u16, EntityState>,
+    /// The `clientdata_t` fields of the local player.
+    pub client_data: Delta,
+    /// The `weapon_data_t` fields of the local player weapons, by weapon index.
+    pub weapons: BTreeMap<u8, Delta>,
+}
+
+/// Reconstructs the full states from the deltas in NetMsg frames.
+///
+/// NetMsg frames must be passed to `update()` in the order they appear in the demo, starting from
+/// the first frame of the first directory entry, since the delta descriptions and the baselines
+/// are sent in the start frames.
+#[derive(Debug, Clone)]
+pub struct StateTracker {
+    decoder: MessageDecoder,
+    baselines: BTreeMap<u16, EntityState>,
+    instanced_baselines: Vec<Delta>,
+    frames: Vec<Option<Snapshot>>,
+    current: Snapshot,
+}
+
+/// Returns the field as a vector, from its `name[0]`, `name[1]` and `name[2]` components.
+///
+/// Missing components are zero.
+pub fn vector(fields: &Delta, name: &str) -> [f32; 3] {
+    let mut rv = [0f32; 3];
+    for (i, x) in rv.iter_mut().enumerate() {
+        *x = fields.get(&format!("{}[{}]", name, i))
+                   .and_then(DeltaValue::as_f32)
+                   .unwrap_or(0f32);
+    }
+    rv
+}
+
+#[inline]
+fn apply(fields: &mut Delta, delta: &Delta) {
+    fields.extend(delta.iter().map(|(k, v)| (k.clone(), v.clone())));
+}
+
+impl EntityState {
+    /// Returns the entity origin.
+    #[inline]
+    pub fn origin(&self) -> [f32; 3] {
+        vector(&self.fields, "origin")
+    }
+
+    /// Returns the entity angles.
+    #[inline]
+    pub fn angles(&self) -> [f32; 3] {
+        vector(&self.fields, "angles")
+    }
+
+    /// Returns the entity velocity.
+    #[inline]
+    pub fn velocity(&self) -> [f32; 3] {
+        vector(&self.fields, "velocity")
+    }
+}
+
+impl StateTracker {
+    /// Creates a state tracker for the given network protocol, as found in
+    /// `Header::net_protocol`.
+    pub fn new(net_protocol: i32) -> Result<Self, Error> {
+        Ok(StateTracker { decoder: MessageDecoder::new(net_protocol)?,
+                          baselines: BTreeMap::new(),
+                          instanced_baselines: Vec::new(),
+                          frames: vec![None; UPDATE_BACKUP],
+                          current: Snapshot::default() })
+    }
+
+    /// Decodes the messages of a NetMsg frame and updates the state.
+    ///
+    /// Returns the decoded messages.
+    pub fn update<'a>(&mut self, data: &'a NetMsgData) -> Result<Vec<Message<'a>>, Error> {
+        let messages = self.decoder.decode(&data.msg)?;
+
+        for message in &messages {
+            match *message {
+                Message::ServerInfo(_) => {
+                    self.baselines.clear();
+                    self.instanced_baselines.clear();
+                    self.frames = vec![None; UPDATE_BACKUP];
+                    self.current = Snapshot::default();
+                }
+                Message::SpawnBaseline(ref baseline) => self.spawn_baseline(baseline),
+                Message::ClientData(ref client_data) => self.apply_client_data(client_data)?,
+                Message::PacketEntities(ref packet) => {
+                    let from = BTreeMap::new();
+                    self.current.entities = self.apply_entities(from, &packet.entities);
+                }
+                Message::DeltaPacketEntities(ref packet) => {
+                    let from = self.frame(packet.delta_sequence)?.entities.clone();
+                    self.current.entities = self.apply_entities(from, &packet.entities);
+                }
+                _ => {}
+            }
+        }
+
+        let slot = data.incoming_sequence as usize % UPDATE_BACKUP;
+        self.frames[slot] = Some(self.current.clone());
+
+        Ok(messages)
+    }
+
+    /// Returns the current state.
+    #[inline]
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.current
+    }
+
+    /// Returns the current entity states, by entity index.
+    #[inline]
+    pub fn entities(&self) -> &BTreeMap<u16, EntityState> {
+        &self.current.entities
+    }
+
+    /// Returns the current entity state of the given entity.
+    #[inline]
+    pub fn entity(&self, index: u16) -> Option<&EntityState> {
+        self.current.entities.get(&index)
+    }
+
+    /// Returns the current `clientdata_t` fields.
+    #[inline]
+    pub fn client_data(&self) -> &Delta {
+        &self.current.client_data
+    }
+
+    /// Returns the current `weapon_data_t` fields, by weapon index.
+    #[inline]
+    pub fn weapons(&self) -> &BTreeMap<u8, Delta> {
+        &self.current.weapons
+    }
+
+    /// Returns the entity baseline of the given entity.
+    #[inline]
+    pub fn baseline(&self, index: u16) -> Option<&EntityState> {
+        self.baselines.get(&index)
+    }
+
+    /// Returns the underlying message decoder.
+    #[inline]
+    pub fn decoder(&self) -> &MessageDecoder {
+        &self.decoder
+    }
+
+    fn frame(&self, sequence: u8) -> Result<&Snapshot, Error> {
+        self.frames[sequence as usize % UPDATE_BACKUP].as_ref()
+                                                     .ok_or(Error::InvalidDeltaSequence(sequence))
+    }
+
+    fn spawn_baseline(&mut self, baseline: &SpawnBaseline) {
+        for entity in &baseline.entities {
+            self.baselines
+                .insert(entity.index,
+                        EntityState { custom: entity.entity_type & ENTITY_BEAM != 0,
+                                      fields: entity.state.clone() });
+        }
+
+        self.instanced_baselines = baseline.instanced.clone();
+    }
+
+    fn apply_client_data(&mut self, client_data: &ClientData) -> Result<(), Error> {
+        let (mut fields, mut weapons) = match client_data.delta_sequence {
+            Some(sequence) => {
+                let from = self.frame(sequence)?;
+                (from.client_data.clone(), from.weapons.clone())
+            }
+            None => (Delta::new(), BTreeMap::new()),
+        };
+
+        apply(&mut fields, &client_data.client_data);
+        for weapon in &client_data.weapons {
+            apply(weapons.entry(weapon.index).or_insert_with(Delta::new), &weapon.data);
+        }
+
+        self.current.client_data = fields;
+        self.current.weapons = weapons;
+        Ok(())
+    }
+
+    /// Applies the entity deltas on top of `from`.
+    fn apply_entities(&self,
+                      mut from: BTreeMap<u16, EntityState>,
+                      deltas: &[EntityDelta])
+                      -> BTreeMap<u16, EntityState> {
+        // Baseline offsets refer to the entities in the order they were sent.
+        let mut sent: Vec<u16> = Vec::with_capacity(deltas.len());
+
+        for delta in deltas {
+            if delta.remove {
+                from.remove(&delta.index);
+                continue;
+            }
+
+            let base = if let Some(index) = delta.instanced_baseline {
+                self.instanced_baselines
+                    .get(index as usize)
+                    .map(|fields| EntityState { custom: false,
+                                                fields: fields.clone() })
+            } else if let Some(offset) = delta.baseline_offset {
+                sent.len()
+                    .checked_sub(offset as usize)
+                    .and_then(|i| from.get(&sent[i]))
+                    .cloned()
+            } else {
+                from.get(&delta.index)
+                    .or_else(|| self.baselines.get(&delta.index))
+                    .cloned()
+            };
+
+            let mut state = base.unwrap_or_default();
+            state.custom = delta.custom;
+            apply(&mut state.fields, &delta.state);
+
+            from.insert(delta.index, state);
+            sent.push(delta.index);
+        }
+
+        from
+    }
+}
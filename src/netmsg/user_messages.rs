@@ -0,0 +1,314 @@
+//! User messages: the registry built from `svc_newusermsg` and typed decoding of the standard
+//! Half-Life and Counter-Strike messages.
+
+use std::collections::HashMap;
+
+use super::bit_reader::BitReader;
+use super::Error;
+
+/// A registered user message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserMessageInfo {
+    pub index: u8,
+    /// The message size, `None` if the size is variable.
+    pub size: Option<u8>,
+    pub name: String,
+}
+
+/// User messages registered with `svc_newusermsg`, by message index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserMessageRegistry {
+    messages: HashMap<u8, UserMessageInfo>,
+}
+
+/// The game the messages are decoded for, when the message layouts differ between games.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Game {
+    HalfLife,
+    CounterStrike,
+}
+
+/// A user message decoded according to its registered name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedUserMessage<'a> {
+    AmmoPickup { ammo_id: u8, count: u8 },
+    AmmoX { ammo_id: u8, count: u8 },
+    BarTime(i16),
+    Battery(i16),
+    CurWeapon { state: u8, weapon_id: u8, clip: i8 },
+    Damage {
+        armor: u8,
+        damage: u8,
+        damage_bits: i32,
+        origin: [f32; 3],
+    },
+    DeathMsg {
+        killer: u8,
+        victim: u8,
+        /// Always `false` outside of Counter-Strike.
+        headshot: bool,
+        weapon: &'a [u8],
+    },
+    FlashBat(u8),
+    Flashlight { on: bool, battery: u8 },
+    Geiger(u8),
+    Health(u8),
+    HideWeapon(u8),
+    HudText(&'a [u8]),
+    InitHud,
+    ItemPickup(&'a [u8]),
+    Money { amount: i32, flash: bool },
+    ResetHud,
+    RoundTime(i16),
+    SayText {
+        client_index: u8,
+        text: &'a [u8],
+        args: Vec<&'a [u8]>,
+    },
+    ScoreAttrib { client_index: u8, flags: u8 },
+    ScoreInfo {
+        client_index: u8,
+        frags: i16,
+        deaths: i16,
+        class: i16,
+        team: i16,
+    },
+    ScreenFade {
+        duration: u16,
+        hold_time: u16,
+        flags: i16,
+        color: [u8; 4],
+    },
+    ScreenShake {
+        amplitude: u16,
+        duration: u16,
+        frequency: u16,
+    },
+    SetFov(u8),
+    StatusIcon {
+        status: u8,
+        sprite: &'a [u8],
+        color: Option<[u8; 3]>,
+    },
+    StatusValue { index: u8, value: i16 },
+    TeamInfo { client_index: u8, team: &'a [u8] },
+    TeamScore { team: &'a [u8], score: i16 },
+    TextMsg {
+        destination: u8,
+        text: &'a [u8],
+        args: Vec<&'a [u8]>,
+    },
+    Train(u8),
+    WeapPickup(u8),
+    WeaponList {
+        name: &'a [u8],
+        primary_ammo_id: i8,
+        primary_ammo_max: u8,
+        secondary_ammo_id: i8,
+        secondary_ammo_max: u8,
+        slot: u8,
+        position: u8,
+        weapon_id: u8,
+        flags: u8,
+    },
+    /// A message without a typed decoder, or which couldn't be decoded.
+    Raw,
+}
+
+impl UserMessageRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a user message, replacing the message with the same index.
+    pub fn register(&mut self, info: UserMessageInfo) {
+        self.messages.insert(info.index, info);
+    }
+
+    /// Returns the message with the given index.
+    #[inline]
+    pub fn get(&self, index: u8) -> Option<&UserMessageInfo> {
+        self.messages.get(&index)
+    }
+
+    /// Returns the message with the given name.
+    pub fn by_name(&self, name: &str) -> Option<&UserMessageInfo> {
+        self.messages.values().find(|info| info.name == name)
+    }
+
+    /// Returns an iterator over the registered messages, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &UserMessageInfo> {
+        self.messages.values()
+    }
+
+    /// Returns the number of registered messages.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if no messages are registered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Removes all registered messages.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
+impl Game {
+    /// Returns the game for the given game directory, as found in `svc_serverinfo`.
+    pub fn from_game_dir(game_dir: &[u8]) -> Self {
+        match game_dir {
+            b"cstrike" | b"czero" => Game::CounterStrike,
+            _ => Game::HalfLife,
+        }
+    }
+}
+
+/// Reads the remaining null-terminated strings.
+fn strings<'a>(r: &mut BitReader<'a>) -> Result<Vec<&'a [u8]>, Error> {
+    let mut rv = Vec::new();
+    while !r.is_empty() {
+        rv.push(r.read_string()?);
+    }
+    Ok(rv)
+}
+
+/// Decodes the user message data according to the message name.
+///
+/// Returns `TypedUserMessage::Raw` for unknown messages.
+pub fn decode<'a>(name: &str, data: &'a [u8], game: Game) -> Result<TypedUserMessage<'a>, Error> {
+    let mut r = BitReader::new(data);
+    let r = &mut r;
+
+    let message = match name {
+        "AmmoPickup" => {
+            TypedUserMessage::AmmoPickup { ammo_id: r.read_u8()?,
+                                           count: r.read_u8()? }
+        }
+        "AmmoX" => {
+            TypedUserMessage::AmmoX { ammo_id: r.read_u8()?,
+                                      count: r.read_u8()? }
+        }
+        "BarTime" => TypedUserMessage::BarTime(r.read_i16()?),
+        "Battery" => TypedUserMessage::Battery(r.read_i16()?),
+        "CurWeapon" => {
+            TypedUserMessage::CurWeapon { state: r.read_u8()?,
+                                          weapon_id: r.read_u8()?,
+                                          clip: r.read_i8()? }
+        }
+        "Damage" => {
+            TypedUserMessage::Damage { armor: r.read_u8()?,
+                                       damage: r.read_u8()?,
+                                       damage_bits: r.read_i32()?,
+                                       origin: [r.read_coord()?, r.read_coord()?, r.read_coord()?] }
+        }
+        "DeathMsg" => {
+            let killer = r.read_u8()?;
+            let victim = r.read_u8()?;
+            let headshot = game == Game::CounterStrike && r.read_u8()? != 0;
+            TypedUserMessage::DeathMsg { killer,
+                                         victim,
+                                         headshot,
+                                         weapon: r.read_string()? }
+        }
+        "FlashBat" => TypedUserMessage::FlashBat(r.read_u8()?),
+        "Flashlight" => {
+            TypedUserMessage::Flashlight { on: r.read_u8()? != 0,
+                                           battery: r.read_u8()? }
+        }
+        "Geiger" => TypedUserMessage::Geiger(r.read_u8()?),
+        "Health" => TypedUserMessage::Health(r.read_u8()?),
+        "HideWeapon" => TypedUserMessage::HideWeapon(r.read_u8()?),
+        "HudText" => TypedUserMessage::HudText(r.read_string()?),
+        "InitHUD" => TypedUserMessage::InitHud,
+        "ItemPickup" => TypedUserMessage::ItemPickup(r.read_string()?),
+        "Money" => {
+            TypedUserMessage::Money { amount: r.read_i32()?,
+                                      flash: r.read_u8()? != 0 }
+        }
+        "ResetHUD" => TypedUserMessage::ResetHud,
+        "RoundTime" => TypedUserMessage::RoundTime(r.read_i16()?),
+        "SayText" => {
+            TypedUserMessage::SayText { client_index: r.read_u8()?,
+                                        text: r.read_string()?,
+                                        args: strings(r)? }
+        }
+        "ScoreAttrib" => {
+            TypedUserMessage::ScoreAttrib { client_index: r.read_u8()?,
+                                            flags: r.read_u8()? }
+        }
+        "ScoreInfo" => {
+            TypedUserMessage::ScoreInfo { client_index: r.read_u8()?,
+                                          frags: r.read_i16()?,
+                                          deaths: r.read_i16()?,
+                                          class: r.read_i16()?,
+                                          team: r.read_i16()? }
+        }
+        "ScreenFade" => {
+            TypedUserMessage::ScreenFade { duration: r.read_u16()?,
+                                           hold_time: r.read_u16()?,
+                                           flags: r.read_i16()?,
+                                           color: [r.read_u8()?,
+                                                   r.read_u8()?,
+                                                   r.read_u8()?,
+                                                   r.read_u8()?] }
+        }
+        "ScreenShake" => {
+            TypedUserMessage::ScreenShake { amplitude: r.read_u16()?,
+                                            duration: r.read_u16()?,
+                                            frequency: r.read_u16()? }
+        }
+        "SetFOV" => TypedUserMessage::SetFov(r.read_u8()?),
+        "StatusIcon" => {
+            let status = r.read_u8()?;
+            let sprite = r.read_string()?;
+            let color = if status != 0 && !r.is_empty() {
+                Some([r.read_u8()?, r.read_u8()?, r.read_u8()?])
+            } else {
+                None
+            };
+            TypedUserMessage::StatusIcon { status,
+                                           sprite,
+                                           color }
+        }
+        "StatusValue" => {
+            TypedUserMessage::StatusValue { index: r.read_u8()?,
+                                            value: r.read_i16()? }
+        }
+        "TeamInfo" => {
+            TypedUserMessage::TeamInfo { client_index: r.read_u8()?,
+                                         team: r.read_string()? }
+        }
+        "TeamScore" => {
+            TypedUserMessage::TeamScore { team: r.read_string()?,
+                                          score: r.read_i16()? }
+        }
+        "TextMsg" => {
+            TypedUserMessage::TextMsg { destination: r.read_u8()?,
+                                        text: r.read_string()?,
+                                        args: strings(r)? }
+        }
+        "Train" => TypedUserMessage::Train(r.read_u8()?),
+        "WeapPickup" => TypedUserMessage::WeapPickup(r.read_u8()?),
+        "WeaponList" => {
+            TypedUserMessage::WeaponList { name: r.read_string()?,
+                                           primary_ammo_id: r.read_i8()?,
+                                           primary_ammo_max: r.read_u8()?,
+                                           secondary_ammo_id: r.read_i8()?,
+                                           secondary_ammo_max: r.read_u8()?,
+                                           slot: r.read_u8()?,
+                                           position: r.read_u8()?,
+                                           weapon_id: r.read_u8()?,
+                                           flags: r.read_u8()? }
+        }
+        _ => TypedUserMessage::Raw,
+    };
+
+    Ok(message)
+}
@@ -0,0 +1,112 @@
+//! Two-pass parallel parsing, enabled with the `rayon` feature.
+//!
+//! The first pass walks the frames of every directory entry and indexes the frame boundaries,
+//! reading only the frame headers and the length fields. The second pass decodes the frames in
+//! parallel chunks.
+
+use rayon::prelude::*;
+
+use errors::*;
+use parse::frame::{self, skip_frame, FrameType};
+use types::*;
+
+/// The number of frames decoded together in the second pass.
+pub const FRAMES_PER_CHUNK: usize = 256;
+
+/// Returns the boundaries of the frames of the entry starting at the given offset, up to and
+/// including the `NextSection` frame.
+///
+/// Returns `None` if a frame is invalid or doesn't fit into the input.
+fn frame_boundaries(input: &[u8], mut offset: usize) -> Option<Vec<(usize, usize)>> {
+    let mut boundaries = Vec::new();
+
+    loop {
+        let (rest, frame_type) = skip_frame(input.get(offset..)?).ok()?;
+        let end = input.len() - rest.len();
+        boundaries.push((offset, end));
+        offset = end;
+
+        if frame_type == FrameType::NextSection {
+            return Some(boundaries);
+        }
+    }
+}
+
+/// Decodes the frames with the given boundaries.
+fn decode<'a>(input: &'a [u8], boundaries: &[(usize, usize)]) -> Option<Vec<Frame<'a>>> {
+    boundaries.par_chunks(FRAMES_PER_CHUNK)
+              .map(|chunk| {
+                       chunk.iter()
+                            .map(|&(start, end)| {
+                                     match frame::frame(&input[start..end]) {
+                                         // The whole frame must be consumed.
+                                         Ok((&[], frame)) => Some(frame),
+                                         _ => None,
+                                     }
+                                 })
+                            .collect::<Option<Vec<_>>>()
+                   })
+              .collect::<Option<Vec<_>>>()
+              .map(|chunks| chunks.into_iter().flatten().collect())
+}
+
+impl<'a> Demo<'a> {
+    /// Parses a demo, decoding the frames in parallel.
+    ///
+    /// The frame boundaries of every directory entry are indexed first, and then the frames of
+    /// each entry are decoded in parallel chunks of `FRAMES_PER_CHUNK` frames. This splits the
+    /// work evenly even when most frames are in a single entry.
+    ///
+    /// The result is the same as from `Demo::parse()`. If the demo fails to parse, it's parsed
+    /// again with `Demo::parse()` to find the error and its position.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::Read;
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut f = File::open("demo.dem")?;
+    /// f.read_to_end(&mut bytes);
+    ///
+    /// let demo = hldemo::Demo::parse_parallel(&bytes)?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn parse_parallel(input: &'a [u8]) -> Result<Demo<'a>> {
+        let mut demo = Demo::parse_without_frames(input)?;
+
+        let boundaries = demo.directory
+                             .entries
+                             .par_iter()
+                             .map(|entry| frame_boundaries(input, entry.offset as usize))
+                             .collect::<Option<Vec<_>>>();
+
+        let frames = boundaries.and_then(|boundaries| {
+                                             boundaries.iter()
+                                                       .map(|b| decode(input, b))
+                                                       .collect::<Option<Vec<_>>>()
+                                         });
+
+        match frames {
+            Some(frames) => {
+                for (entry, frames) in demo.directory.entries.iter_mut().zip(frames) {
+                    entry.frames = frames;
+                }
+                Ok(demo)
+            }
+            None => Demo::parse(input),
+        }
+    }
+}
@@ -9,6 +9,15 @@ use types::*;
 pub const MIN_ENTRY_COUNT: i32 = 1;
 pub const MAX_ENTRY_COUNT: i32 = 1024;
 
+/// The entry type of the loading segment.
+pub const ENTRY_TYPE_LOADING: i32 = 0;
+
+/// The entry type of the playback segments.
+pub const ENTRY_TYPE_PLAYBACK: i32 = 1;
+
+/// The size of a directory entry in bytes.
+pub const ENTRY_SIZE: usize = 92;
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 named!(pub entry<DirectoryEntry>,
     do_parse!(
@@ -23,7 +32,7 @@ named!(pub entry<DirectoryEntry>,
         (
             DirectoryEntry {
                 entry_type,
-                description,
+                description: Cow::Borrowed(description),
                 flags,
                 cd_track,
                 track_time,
@@ -71,14 +80,40 @@ pub fn offset_directory(input: &[u8], offset: usize) -> IResult<&[u8], Directory
     )
 }
 
+/// Calls the function with the index of every directory entry and the entry.
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn map_entries<T, F>(entries: &[DirectoryEntry], f: F) -> Vec<T>
+    where F: Fn(usize, &DirectoryEntry) -> T
+{
+    entries.iter().enumerate().map(|(i, entry)| f(i, entry)).collect()
+}
+
+/// Calls the function with the index of every directory entry and the entry, in parallel.
+#[cfg(feature = "rayon")]
+pub(crate) fn map_entries<T, F>(entries: &[DirectoryEntry], f: F) -> Vec<T>
+    where T: Send,
+          F: Fn(usize, &DirectoryEntry) -> T + Sync
+{
+    use rayon::prelude::*;
+
+    entries.par_iter().enumerate().map(|(i, entry)| f(i, entry)).collect()
+}
+
+/// Parses the directory at the given offset, along with the frames of every entry.
+///
+/// With the `rayon` feature, the entries are parsed in parallel. The error of the first entry
+/// which fails to parse is returned either way.
 #[inline]
 pub fn offset_directory_with_frames(input: &[u8],
                                     offset: usize)
                                     -> IResult<&[u8], Directory, Error> {
     match offset_directory(input, offset) {
         Ok((_, mut directory)) => {
-            for entry in &mut directory.entries {
-                entry.frames = match offset_frames(input, entry.offset as usize) {
+            let frames = map_entries(&directory.entries,
+                                     |_, entry| offset_frames(input, entry.offset as usize));
+
+            for (entry, frames) in directory.entries.iter_mut().zip(frames) {
+                entry.frames = match frames {
                     Ok((_, frames)) => frames,
                     other => return other.map(|_| unreachable!()),
                 };
@@ -23,6 +23,9 @@ use self::weapon_anim::*;
 
 pub const MAX_FRAME_TYPE: u8 = 9;
 
+/// The size of a frame header in bytes.
+pub const FRAME_HEADER_SIZE: usize = 9;
+
 /// An enum containing the possible frame types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FrameType {
@@ -37,6 +40,57 @@ pub enum FrameType {
     DemoBuffer,
 }
 
+/// The layout of the frame data of a frame type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataLayout {
+    /// The size of the frame data in bytes, not counting the variable-length field.
+    pub fixed_size: usize,
+    /// The offset into the frame data of the `i32` length of the variable-length field, if there
+    /// is one.
+    pub length_offset: Option<usize>,
+}
+
+impl FrameType {
+    /// Returns the frame type with the given raw value.
+    #[inline]
+    pub fn from_raw(frame_type: u8) -> Result<Self, Error> {
+        parse_frame_type(frame_type)
+    }
+
+    /// Returns the layout of the frame data.
+    pub fn data_layout(self) -> DataLayout {
+        let (fixed_size, length_offset) = match self {
+            FrameType::NetMsg(_) => (NET_MSG_DATA_SIZE, Some(NET_MSG_DATA_SIZE - 4)),
+            FrameType::DemoStart | FrameType::NextSection => (0, None),
+            FrameType::ConsoleCommand => (64, None),
+            FrameType::ClientData => (32, None),
+            FrameType::Event => (84, None),
+            FrameType::WeaponAnim => (8, None),
+            FrameType::Sound => (24, Some(4)),
+            FrameType::DemoBuffer => (4, Some(0)),
+        };
+
+        DataLayout { fixed_size,
+                     length_offset }
+    }
+
+    /// Returns the size of the frame data in bytes, given the value of the length field.
+    ///
+    /// Fails if the length of a NetMsg is out of range. Negative lengths of the other fields are
+    /// counted as zero, the frame data then fails to parse.
+    pub fn data_size(self, length: i32) -> Result<usize, Error> {
+        let layout = self.data_layout();
+
+        match (self, layout.length_offset) {
+            (FrameType::NetMsg(_), _) => {
+                check_msg_length(length).map(|length| layout.fixed_size + length as usize)
+            }
+            (_, Some(_)) => Ok(layout.fixed_size + length.max(0) as usize),
+            (_, None) => Ok(layout.fixed_size),
+        }
+    }
+}
+
 /// A demo frame header.
 ///
 /// Every frame starts with a header, followed by frame data depending on the frame type.
@@ -132,6 +186,27 @@ named!(pub frame<&[u8], Frame, Error>,
     )
 );
 
+/// Skips a frame, returning its type.
+///
+/// Only the frame header and the length field are read, the rest of the frame data isn't parsed.
+pub fn skip_frame(input: &[u8]) -> IResult<&[u8], FrameType, Error> {
+    let (data, header) = frame_header(input)?;
+    let frame_type = header.frame_type;
+
+    let length = match frame_type.data_layout().length_offset {
+        Some(offset) => {
+            let (_, length) = fix_error!(data, Error, preceded!(take!(offset), le_i32))?;
+            length
+        }
+        None => 0,
+    };
+
+    match frame_type.data_size(length) {
+        Ok(size) => fix_error!(data, Error, take!(size)).map(|(rest, _)| (rest, frame_type)),
+        Err(err) => Err(Err::Error(Context::Code(data, ErrorKind::Custom(err)))),
+    }
+}
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 named!(pub frames<&[u8], Vec<Frame>, Error>,
     add_parse_error!(Frames,
@@ -5,6 +5,11 @@ use super::*;
 pub const MIN_MESSAGE_LENGTH: i32 = 0;
 pub const MAX_MESSAGE_LENGTH: i32 = 65536;
 
+/// The size of NetMsg frame data in bytes, not counting the message itself.
+///
+/// The message length is stored in the last four bytes.
+pub const NET_MSG_DATA_SIZE: usize = 468;
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 named!(ref_params<RefParams>,
     do_parse!(
@@ -166,7 +171,7 @@ named!(movevars<MoveVars>,
                 zmax,
                 wave_height,
                 footsteps,
-                sky_name,
+                sky_name: Cow::Borrowed(sky_name),
                 rollangle,
                 rollspeed,
                 skycolor_r,
@@ -203,7 +208,7 @@ named!(net_msg_info<NetMsgInfo>,
 );
 
 #[inline]
-fn check_msg_length(length: i32) -> Result<i32, Error> {
+pub(crate) fn check_msg_length(length: i32) -> Result<i32, Error> {
     if length < MIN_MESSAGE_LENGTH || length > MAX_MESSAGE_LENGTH {
         Err(Error::InvalidNetMsgLength(length))
     } else {
@@ -236,7 +241,7 @@ named!(pub net_msg_data<&[u8], NetMsgData, Error>,
                 outgoing_sequence,
                 reliable_sequence,
                 last_reliable_sequence,
-                msg,
+                msg: Cow::Borrowed(msg),
             }
         )
     )
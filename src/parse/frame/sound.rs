@@ -15,7 +15,7 @@ named!(pub sound_data<FrameData>,
             FrameData::Sound(
                 SoundData {
                     channel,
-                    sample,
+                    sample: Cow::Borrowed(sample),
                     attenuation,
                     volume,
                     flags,
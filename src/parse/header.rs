@@ -7,6 +7,12 @@ use types::*;
 
 pub const SUPPORTED_DEMO_PROTOCOL: i32 = 5;
 
+/// The magic value every demo starts with, including the padding.
+pub const MAGIC: &[u8] = b"HLDEMO\0\0";
+
+/// The size of the demo header in bytes.
+pub const HEADER_SIZE: usize = 544;
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 named!(pub magic<&[u8], (), Error>,
     add_parse_error!(InvalidMagic,
@@ -43,8 +49,8 @@ named!(pub header<&[u8], Header, Error>,
                 Header {
                     demo_protocol,
                     net_protocol,
-                    map_name,
-                    game_dir,
+                    map_name: Cow::Borrowed(map_name),
+                    game_dir: Cow::Borrowed(game_dir),
                     map_crc,
                     directory_offset,
                 }
@@ -28,15 +28,15 @@ macro_rules! map_res_err_ (
     }
   );
   ($i:expr, $submac:ident!( $($args:tt)* ), $g:expr) => (
-    map_res_err_!(__impl $i, $submac!($($args)*), call!($g));
+    map_res_err_!(__impl $i, $submac!($($args)*), call!($g))
   );
   ($i:expr, $submac:ident!( $($args:tt)* ), $submac2:ident!( $($args2:tt)* )) => (
-    map_res_err_!(__impl $i, $submac!($($args)*), $submac2!($($args2)*));
+    map_res_err_!(__impl $i, $submac!($($args)*), $submac2!($($args2)*))
   );
   ($i:expr, $f:expr, $g:expr) => (
-    map_res_err_!(__impl $i, call!($f), call!($g));
+    map_res_err_!(__impl $i, call!($f), call!($g))
   );
   ($i:expr, $f:expr, $submac:ident!( $($args:tt)* )) => (
-    map_res_err_!(__impl $i, call!($f), $submac!($($args)*));
+    map_res_err_!(__impl $i, call!($f), $submac!($($args)*))
   );
 );
@@ -1,6 +1,7 @@
 //! Nom parsing functions for demo parsing.
 
 use nom::*;
+use std::borrow::Cow;
 
 #[macro_use]
 mod macros;
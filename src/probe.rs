@@ -0,0 +1,140 @@
+//! Quick inspection of files which may not be valid demos.
+
+use nom;
+
+use byte_string::lossy_string;
+use parse::directory::{offset_directory, ENTRY_TYPE_LOADING};
+use parse::header::{header, HEADER_SIZE, MAGIC};
+
+/// The magic value Xash3D demos start with.
+const XASH_MAGIC: &[u8] = b"IDEM";
+
+/// The engine which recorded the demo, guessed from the magic value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// The Goldsource engine, the only one supported by the parser.
+    GoldSrc,
+    /// The Xash3D engine, whose demos can't be parsed.
+    Xash3D,
+    /// The magic value isn't recognized.
+    Unknown,
+}
+
+/// The information found by `probe()`.
+///
+/// Fields are `None` when they couldn't be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    /// The magic value, shorter than `MAGIC` if the input is.
+    pub magic: Vec<u8>,
+    pub dialect: Dialect,
+    pub demo_protocol: Option<i32>,
+    pub net_protocol: Option<i32>,
+    pub map_name: Option<String>,
+    pub game_dir: Option<String>,
+    /// The total track time of the playback entries, from the directory.
+    pub playback_duration: Option<f32>,
+    /// `true` if the file ends before the header, the directory or the data of a directory entry.
+    pub truncated: bool,
+}
+
+/// Returns the little-endian `i32` at the given offset.
+#[inline]
+fn i32_at(input: &[u8], offset: usize) -> Option<i32> {
+    input.get(offset..).and_then(|i| nom::le_i32(i).ok()).map(|(_, x)| x)
+}
+
+/// Inspects the input without parsing the frames.
+///
+/// Reports as much as can be read from the header and the directory, even if the input isn't a
+/// valid demo. Never panics, whatever the input.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::error::Error;
+/// #
+/// # fn try_main() -> Result<(), Box<Error>> {
+/// extern crate hldemo;
+///
+/// use std::fs::File;
+/// use std::io::Read;
+///
+/// let mut bytes = Vec::new();
+/// let mut f = File::open("unknown.dem")?;
+/// f.read_to_end(&mut bytes);
+///
+/// let probe = hldemo::probe(&bytes);
+/// println!("{:?} demo, protocol {:?}", probe.dialect, probe.demo_protocol);
+/// if probe.truncated {
+///     println!("the demo is truncated");
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+pub fn probe(input: &[u8]) -> Probe {
+    let magic = &input[..input.len().min(MAGIC.len())];
+    let dialect = if magic.starts_with(&MAGIC[..6]) {
+        Dialect::GoldSrc
+    } else if magic.starts_with(XASH_MAGIC) {
+        Dialect::Xash3D
+    } else {
+        Dialect::Unknown
+    };
+
+    let mut probe = Probe { magic: magic.to_vec(),
+                            dialect,
+                            demo_protocol: None,
+                            net_protocol: None,
+                            map_name: None,
+                            game_dir: None,
+                            playback_duration: None,
+                            truncated: false };
+
+    // The layout of the other dialects isn't known.
+    if dialect != Dialect::GoldSrc {
+        return probe;
+    }
+
+    // The protocols are read directly, so that they're reported even if they're unsupported.
+    probe.demo_protocol = i32_at(input, MAGIC.len());
+    probe.net_protocol = i32_at(input, MAGIC.len() + 4);
+    probe.truncated = input.len() < HEADER_SIZE;
+
+    let header = match header(input) {
+        Ok((_, header)) => header,
+        Err(_) => return probe,
+    };
+
+    probe.map_name = Some(lossy_string(&header.map_name));
+    probe.game_dir = Some(lossy_string(&header.game_dir));
+
+    if header.directory_offset < 0 {
+        return probe;
+    }
+
+    let directory = match offset_directory(input, header.directory_offset as usize) {
+        Ok((_, directory)) => directory,
+        Err(nom::Err::Incomplete(_)) => {
+            probe.truncated = true;
+            return probe;
+        }
+        Err(_) => return probe,
+    };
+
+    probe.playback_duration = Some(directory.entries
+                                            .iter()
+                                            .filter(|e| e.entry_type != ENTRY_TYPE_LOADING)
+                                            .map(|e| e.track_time)
+                                            .sum());
+    probe.truncated = directory.entries.iter().any(|e| {
+                          i64::from(e.offset) + i64::from(e.file_length) > input.len() as i64
+                      });
+
+    probe
+}
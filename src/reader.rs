@@ -0,0 +1,258 @@
+//! Reading demos from `Read + Seek` sources.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use nom;
+
+use errors::*;
+use iter::FrameIter;
+use parse;
+use parse::directory::{directory, ENTRY_SIZE};
+use parse::frame::{frame_header, FrameType, FRAME_HEADER_SIZE};
+use parse::header::{header, HEADER_SIZE};
+use types::*;
+
+/// A demo reader which reads data on demand instead of requiring the whole demo in memory.
+///
+/// The header and the directory are read when the reader is created. Frames are read one
+/// directory entry at a time with `read_entry()`, or all at once with `read_demo()`. All returned
+/// data is owned and doesn't borrow from the reader.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::error::Error;
+/// #
+/// # fn try_main() -> Result<(), Box<Error>> {
+/// extern crate hldemo;
+///
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let f = File::open("demo.dem")?;
+/// let mut reader = hldemo::DemoReader::new(BufReader::new(f))?;
+///
+/// for i in 0..reader.directory().entries.len() {
+///     let entry = reader.read_entry(i)?;
+///     for frame in entry.frames() {
+///         let frame = frame?;
+///         println!("{}", frame.time);
+///     }
+/// }
+/// #
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DemoReader<R> {
+    reader: R,
+    header: Header<'static>,
+    directory: Directory<'static>,
+}
+
+/// Frame data of a directory entry read by a `DemoReader`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryFrames {
+    bytes: Vec<u8>,
+    offset: usize,
+    entry: usize,
+}
+
+impl<R: Read + Seek> DemoReader<R> {
+    /// Creates a demo reader, reading the demo header and directory.
+    pub fn new(mut reader: R) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+
+        let header_position = Position { offset: 0,
+                                         entry: None,
+                                         frame: None };
+        let mut header_bytes = Vec::with_capacity(HEADER_SIZE);
+        read_bytes(&mut reader, &mut header_bytes, HEADER_SIZE, header_position)?;
+        let header = match header(&header_bytes) {
+            Ok((_, header)) => header.into_owned(),
+            Err(err) => return Err(nom_error(err, &header_bytes, header_position)),
+        };
+        let directory_offset = header.directory_offset as u32;
+
+        reader.seek(SeekFrom::Start(u64::from(directory_offset)))?;
+
+        let directory_position = Position { offset: directory_offset as usize,
+                                            ..header_position };
+        let mut directory_bytes = Vec::new();
+        read_bytes(&mut reader, &mut directory_bytes, 4, directory_position)?;
+
+        // Make sure the count is valid before reading the entries.
+        if let Err(err @ nom::Err::Error(_)) = directory(&directory_bytes) {
+            return Err(nom_error(err, &directory_bytes, directory_position));
+        }
+
+        let count = le_i32(&directory_bytes) as usize;
+        read_bytes(&mut reader,
+                   &mut directory_bytes,
+                   count * ENTRY_SIZE,
+                   directory_position)?;
+        let directory = match directory(&directory_bytes) {
+            Ok((_, directory)) => directory.into_owned(),
+            Err(err) => return Err(nom_error(err, &directory_bytes, directory_position)),
+        };
+
+        Ok(DemoReader { reader,
+                        header,
+                        directory })
+    }
+
+    /// Returns the demo header.
+    #[inline]
+    pub fn header(&self) -> &Header<'static> {
+        &self.header
+    }
+
+    /// Returns the demo directory.
+    ///
+    /// The directory entries don't contain any frames, use `read_entry()` to read them.
+    #[inline]
+    pub fn directory(&self) -> &Directory<'static> {
+        &self.directory
+    }
+
+    /// Reads the frames of the directory entry with the given index.
+    ///
+    /// Frames are read starting at the entry offset until the `NextSection` frame.
+    ///
+    /// Fails if there's no directory entry with the given index.
+    pub fn read_entry(&mut self, index: usize) -> Result<EntryFrames> {
+        let count = self.directory.entries.len();
+        let offset = match self.directory.entries.get(index) {
+            Some(entry) => entry.offset as u32,
+            None => bail!(ErrorKind::InvalidEntryIndex(index, count)),
+        };
+        self.reader.seek(SeekFrom::Start(u64::from(offset)))?;
+
+        let mut bytes = Vec::new();
+        for frame in 0.. {
+            let position = Position { offset: offset as usize,
+                                      entry: Some(index),
+                                      frame: Some(frame) };
+
+            match read_frame(&mut self.reader, &mut bytes, position) {
+                Ok(FrameType::NextSection) => break,
+                Ok(_) => {}
+                Err(err) => return Err(Error::with_chain(err, Error::from(parse::Error::Frames))),
+            }
+        }
+
+        Ok(EntryFrames { bytes,
+                         offset: offset as usize,
+                         entry: index })
+    }
+
+    /// Reads the whole demo, including the frames of every directory entry.
+    pub fn read_demo(&mut self) -> Result<Demo<'static>> {
+        let mut directory = self.directory.clone();
+        for (i, entry) in directory.entries.iter_mut().enumerate() {
+            entry.frames = self.read_entry(i)?.into_frames()?;
+        }
+
+        Ok(Demo { header: self.header.clone(),
+                  directory })
+    }
+
+    /// Unwraps this `DemoReader`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl EntryFrames {
+    /// Returns an iterator over the frames.
+    pub fn frames(&self) -> FrameIter<'_> {
+        FrameIter::new(&self.bytes).with_base(self.offset)
+                                   .for_entry(self.entry)
+    }
+
+    /// Parses all frames into a `Vec`.
+    pub fn parse(&self) -> Result<Vec<Frame<'_>>> {
+        self.frames().collect()
+    }
+
+    /// Parses all frames into a `Vec` of owned frames.
+    pub fn into_frames(self) -> Result<Vec<Frame<'static>>> {
+        self.frames().map(|frame| frame.map(Frame::into_owned)).collect()
+    }
+
+    /// Returns the raw frame data.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[inline]
+fn le_i32(bytes: &[u8]) -> i32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[bytes.len() - 4..]);
+    i32::from_le_bytes(buf)
+}
+
+/// Converts a nom error from parsing `input`, which starts at `position`.
+fn nom_error(err: nom::Err<&[u8], parse::Error>, input: &[u8], position: Position) -> Error {
+    let offset = position.offset + nom_offset(&err, input);
+    Error::from_nom_at(err, Position { offset, ..position })
+}
+
+/// Reads exactly `count` bytes, appending them to `buf`.
+///
+/// `position` is the position of the start of `buf`, used for errors.
+fn read_bytes<R: Read>(reader: &mut R,
+                       buf: &mut Vec<u8>,
+                       count: usize,
+                       position: Position)
+                       -> Result<()> {
+    let read = reader.take(count as u64).read_to_end(buf)?;
+    if read < count {
+        let position = Position { offset: position.offset + buf.len(),
+                                  ..position };
+        return Err(position.error(ErrorKind::NeedMoreBytes(Some(count - read))));
+    }
+
+    Ok(())
+}
+
+/// Reads a raw frame, appending it to `buf`.
+///
+/// `position` is the position of the start of `buf`, used for errors. Returns the frame type.
+fn read_frame<R: Read>(reader: &mut R, buf: &mut Vec<u8>, position: Position) -> Result<FrameType> {
+    let start = buf.len();
+    let frame_position = Position { offset: position.offset + start,
+                                    ..position };
+
+    read_bytes(reader, buf, FRAME_HEADER_SIZE, position)?;
+    let frame_type = match frame_header(&buf[start..]) {
+        Ok((_, header)) => header.frame_type,
+        Err(err) => return Err(nom_error(err, &buf[start..], frame_position)),
+    };
+
+    // Read up to and including the length field to find out the size of the rest.
+    let layout = frame_type.data_layout();
+    let rest = match layout.length_offset {
+        Some(length_offset) => {
+            read_bytes(reader, buf, length_offset + 4, position)?;
+            match frame_type.data_size(le_i32(buf)) {
+                Ok(size) => size - (length_offset + 4),
+                Err(err) => {
+                    let position = Position { offset: position.offset + buf.len() - 4,
+                                              ..position };
+                    return Err(position.error(ErrorKind::ParseError(err)));
+                }
+            }
+        }
+        None => layout.fixed_size,
+    };
+    read_bytes(reader, buf, rest, position)?;
+
+    Ok(frame_type)
+}
@@ -0,0 +1,193 @@
+//! Lenient parsing of truncated and corrupted demos.
+
+use std::fmt;
+
+use errors::*;
+use iter::FrameIter;
+use parse;
+use parse::directory::{offset_directory, ENTRY_TYPE_LOADING, ENTRY_TYPE_PLAYBACK};
+use parse::header::{header, HEADER_SIZE};
+use types::*;
+
+/// A problem found while parsing a demo with `Demo::parse_lenient()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The directory couldn't be parsed, so the frames were scanned sequentially after the header.
+    InvalidDirectory { message: String },
+    /// A frame couldn't be parsed. The directory entry ends before this frame, and when scanning,
+    /// nothing after it was parsed.
+    InvalidFrame { position: Position, message: String },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Diagnostic::InvalidDirectory { ref message } => {
+                write!(f, "invalid directory, scanned the frames instead: {}", message)
+            }
+            Diagnostic::InvalidFrame { ref position,
+                                       ref message, } => {
+                write!(f, "{} ({})", message, position)
+            }
+        }
+    }
+}
+
+/// Returns the error message, excluding the position.
+fn message(err: &Error) -> String {
+    let count = err.iter().count() - if err.position().is_some() { 1 } else { 0 };
+    err.iter()
+       .take(count)
+       .map(|e| e.to_string())
+       .collect::<Vec<_>>()
+       .join(": ")
+}
+
+/// Parses frames until the `NextSection` frame or the first error.
+///
+/// Returns the frames, the offset after the last parsed frame and the diagnostic for the error.
+fn entry_frames<'a>(input: &'a [u8],
+                    offset: usize,
+                    entry: usize)
+                    -> (Vec<Frame<'a>>, usize, Option<Diagnostic>) {
+    let mut iter = FrameIter::with_offset(input, offset).for_entry(entry);
+    let mut frames = Vec::new();
+
+    for frame in iter.by_ref() {
+        match frame {
+            Ok(frame) => frames.push(frame),
+            Err(err) => {
+                let diagnostic = Diagnostic::InvalidFrame { position: err.position().unwrap(),
+                                                            message: message(&err) };
+                return (frames, iter.offset(), Some(diagnostic));
+            }
+        }
+    }
+
+    (frames, iter.offset(), None)
+}
+
+/// Creates a directory entry for frames found by scanning.
+fn scanned_entry<'a>(index: usize,
+                     offset: usize,
+                     end: usize,
+                     frames: Vec<Frame<'a>>)
+                     -> DirectoryEntry<'a> {
+    let entry_type = if index == 0 {
+        ENTRY_TYPE_LOADING
+    } else {
+        ENTRY_TYPE_PLAYBACK
+    };
+
+    DirectoryEntry { offset: offset as i32,
+                     file_length: (end - offset) as i32,
+                     ..DirectoryEntry::from_frames(entry_type, frames) }
+}
+
+/// Scans the frames sequentially from the end of the header.
+fn scan_entries<'a>(input: &'a [u8], diagnostics: &mut Vec<Diagnostic>) -> Directory<'a> {
+    let mut entries = Vec::new();
+    let mut offset = HEADER_SIZE;
+
+    while offset < input.len() {
+        let index = entries.len();
+        let (frames, end, diagnostic) = entry_frames(input, offset, index);
+
+        if !frames.is_empty() {
+            entries.push(scanned_entry(index, offset, end, frames));
+        }
+
+        if let Some(diagnostic) = diagnostic {
+            diagnostics.push(diagnostic);
+            break;
+        }
+
+        offset = end;
+    }
+
+    Directory { entries }
+}
+
+impl<'a> Demo<'a> {
+    /// Parses a demo, recovering as much data as possible from truncated and corrupted demos.
+    ///
+    /// If the directory can't be parsed, the frames are scanned sequentially starting right after
+    /// the header, up to the first frame which can't be parsed. Otherwise, the frames of every
+    /// directory entry are parsed up to the first frame which can't be parsed. The problems found
+    /// are returned alongside the demo.
+    ///
+    /// Only errors in the header are returned as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::Read;
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut f = File::open("demo.dem")?;
+    /// f.read_to_end(&mut bytes);
+    ///
+    /// let (demo, diagnostics) = hldemo::Demo::parse_lenient(&bytes)?;
+    /// for diagnostic in diagnostics {
+    ///     println!("{}", diagnostic);
+    /// }
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn parse_lenient(input: &'a [u8]) -> Result<(Demo<'a>, Vec<Diagnostic>)> {
+        let header = match header(input) {
+            Ok((_, header)) => header,
+            Err(err) => {
+                let position = Position { offset: nom_offset(&err, input),
+                                          entry: None,
+                                          frame: None };
+                return Err(Error::from_nom_at(err, position));
+            }
+        };
+
+        let mut diagnostics = Vec::new();
+
+        let directory = if header.directory_offset < HEADER_SIZE as i32 {
+            Err(Error::from(parse::Error::Directory))
+        } else {
+            match offset_directory(input, header.directory_offset as usize) {
+                Ok((_, directory)) => Ok(directory),
+                Err(err) => {
+                    let position = Position { offset: nom_offset(&err, input),
+                                              entry: None,
+                                              frame: None };
+                    Err(Error::from_nom_at(err, position))
+                }
+            }
+        };
+
+        let directory = match directory {
+            Ok(mut directory) => {
+                for (i, entry) in directory.entries.iter_mut().enumerate() {
+                    let (frames, _, diagnostic) = entry_frames(input, entry.offset as usize, i);
+                    entry.frames = frames;
+                    diagnostics.extend(diagnostic);
+                }
+
+                directory
+            }
+            Err(err) => {
+                diagnostics.push(Diagnostic::InvalidDirectory { message: message(&err) });
+                scan_entries(input, &mut diagnostics)
+            }
+        };
+
+        Ok((Demo { header, directory }, diagnostics))
+    }
+}
@@ -0,0 +1,186 @@
+//! Reconstruction of the demo directory from the frames.
+
+use std::borrow::Cow;
+
+use errors::*;
+use parse::directory::{ENTRY_TYPE_LOADING, ENTRY_TYPE_PLAYBACK};
+use parse::header::HEADER_SIZE;
+use recover::Diagnostic;
+use types::*;
+use write::frame::frames_size;
+
+impl<'a> DirectoryEntry<'a> {
+    /// Creates a directory entry with the given frames.
+    ///
+    /// Entries of type `ENTRY_TYPE_LOADING` are described as `LOADING`, and the rest as
+    /// `Playback`, like the engine does. The track time and the frame count are computed from the
+    /// frames, while the offset and the file length are left at zero until
+    /// `Directory::rebuild()` is called.
+    pub fn from_frames(entry_type: i32, frames: Vec<Frame<'a>>) -> Self {
+        let description: &'static [u8] = if entry_type == ENTRY_TYPE_LOADING {
+            b"LOADING"
+        } else {
+            b"Playback"
+        };
+
+        let mut entry = DirectoryEntry { entry_type,
+                                         description: Cow::Borrowed(description),
+                                         flags: 0,
+                                         cd_track: -1,
+                                         track_time: 0f32,
+                                         frame_count: 0,
+                                         offset: 0,
+                                         file_length: 0,
+                                         frames };
+        entry.update_counts();
+        entry
+    }
+
+    /// Recomputes the track time and the frame count from the frames.
+    ///
+    /// The engine leaves both at zero for the loading segment.
+    pub fn update_counts(&mut self) {
+        let (track_time, frame_count) = match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) if self.entry_type != ENTRY_TYPE_LOADING => {
+                (last.time - first.time, last.frame - first.frame + 1)
+            }
+            _ => (0f32, 0),
+        };
+
+        self.track_time = track_time;
+        self.frame_count = frame_count;
+    }
+
+    /// Appends a `NextSection` frame if the entry doesn't end with one.
+    fn terminate(&mut self) {
+        let (time, frame) = match self.frames.last() {
+            Some(&Frame { data: FrameData::NextSection, .. }) => return,
+            Some(last) => (last.time, last.frame),
+            None => (0f32, 0),
+        };
+
+        self.frames.push(Frame { time,
+                                 frame,
+                                 data: FrameData::NextSection });
+    }
+}
+
+impl<'a> Directory<'a> {
+    /// Creates a directory from a sequence of frames, starting a new entry after every
+    /// `NextSection` frame.
+    ///
+    /// The first entry is the loading segment and the rest are playback segments. The directory is
+    /// rebuilt with `rebuild()`, so it's consistent with the frames.
+    pub fn from_frames<I: IntoIterator<Item = Frame<'a>>>(frames: I) -> Self {
+        let mut entries = Vec::new();
+        let mut current = Vec::new();
+
+        for frame in frames {
+            let next_section = frame.data == FrameData::NextSection;
+            current.push(frame);
+
+            if next_section {
+                entries.push(current);
+                current = Vec::new();
+            }
+        }
+
+        if !current.is_empty() {
+            entries.push(current);
+        }
+
+        let entries = entries.into_iter().enumerate().map(|(i, frames)| {
+            let entry_type = if i == 0 {
+                ENTRY_TYPE_LOADING
+            } else {
+                ENTRY_TYPE_PLAYBACK
+            };
+            DirectoryEntry::from_frames(entry_type, frames)
+        });
+
+        let mut directory = Directory { entries: entries.collect() };
+        directory.rebuild();
+        directory
+    }
+
+    /// Makes the directory consistent with the frames.
+    ///
+    /// A `NextSection` frame is appended to every entry which doesn't end with one. The offset,
+    /// the file length, the track time and the frame count of every entry are recomputed, with
+    /// the entries laid out right after the header in order, like `Demo::write()` does.
+    pub fn rebuild(&mut self) {
+        let mut offset = HEADER_SIZE;
+
+        for entry in &mut self.entries {
+            entry.terminate();
+            entry.update_counts();
+
+            let length = frames_size(&entry.frames);
+            entry.offset = offset as i32;
+            entry.file_length = length as i32;
+            offset += length;
+        }
+    }
+
+    /// Returns the offset of the directory when the demo is written.
+    fn written_offset(&self) -> usize {
+        HEADER_SIZE + self.entries.iter().map(|e| frames_size(&e.frames)).sum::<usize>()
+    }
+}
+
+impl<'a> Demo<'a> {
+    /// Rebuilds the directory with `Directory::rebuild()` and updates the directory offset.
+    pub fn rebuild_directory(&mut self) {
+        self.directory.rebuild();
+        self.header.directory_offset = self.directory.written_offset() as i32;
+    }
+
+    /// Parses a damaged demo with `Demo::parse_lenient()` and rebuilds its directory, so that it
+    /// can be written as a valid demo.
+    ///
+    /// Entries without any frames are removed. Fails with `ErrorKind::NoFrames` if that leaves no
+    /// entries.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::{BufWriter, Read};
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut f = File::open("crashed.dem")?;
+    /// f.read_to_end(&mut bytes);
+    ///
+    /// let (demo, diagnostics) = hldemo::Demo::repair(&bytes)?;
+    /// for diagnostic in diagnostics {
+    ///     println!("{}", diagnostic);
+    /// }
+    ///
+    /// demo.write(BufWriter::new(File::create("fixed.dem")?))?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn repair(input: &'a [u8]) -> Result<(Demo<'a>, Vec<Diagnostic>)> {
+        let (mut demo, diagnostics) = Demo::parse_lenient(input)?;
+
+        // Entries without any frames can't be played back.
+        demo.directory.entries.retain(|entry| !entry.frames.is_empty());
+        if demo.directory.entries.is_empty() {
+            bail!(ErrorKind::NoFrames);
+        }
+
+        demo.rebuild_directory();
+
+        Ok((demo, diagnostics))
+    }
+}
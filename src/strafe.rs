@@ -0,0 +1,325 @@
+//! Strafe and bunnyhop analysis.
+//!
+//! The analysis walks the NetMsg frames of a playback segment and uses the player command from
+//! `UserCmd` together with the player state from `RefParams` and the movement variables from
+//! `MoveVars`. Since `RefParams::simvel` is the velocity after the frame's movement, the velocity
+//! of the previous frame is used as the velocity the command was applied to.
+//!
+//! HLTV demos don't record the player commands, so `ErrorKind::HltvDemo` is returned for them.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! let mut bytes = Vec::new();
+//! let mut f = File::open("demo.dem")?;
+//! f.read_to_end(&mut bytes);
+//!
+//! let demo = hldemo::Demo::parse(&bytes)?;
+//! let analysis = hldemo::strafe::analyze(&demo.directory.entries[1])?;
+//!
+//! println!("{} jumps, {} bunnyhops in a row at most",
+//!          analysis.jumps.len(),
+//!          analysis.max_bunnyhops);
+//! for strafe in &analysis.strafes {
+//!     println!("{:?}: sync {:.0}%, gain {:.2} ups",
+//!              strafe.direction,
+//!              strafe.sync * 100f32,
+//!              strafe.gain);
+//! }
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use std::f32::consts::PI;
+
+use errors::*;
+use types::*;
+
+/// The wish speed is capped at this value when accelerating in the air.
+pub const AIR_WISHSPEED_CAP: f32 = 30f32;
+
+/// A jump is a bunnyhop if the player spent at most this many frames on the ground before it.
+pub const MAX_BUNNYHOP_GROUND_FRAMES: usize = 1;
+
+/// A jump off the ground.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jump {
+    pub time: f32,
+    pub frame: i32,
+    /// The horizontal speed at the jump.
+    pub speed: f32,
+    /// The number of frames spent on the ground before the jump.
+    pub ground_frames: usize,
+    /// The number of consecutive bunnyhops ending with this jump, zero if it isn't a bunnyhop.
+    pub bunnyhops: usize,
+}
+
+/// The strafe direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StrafeDirection {
+    Left,
+    Right,
+}
+
+/// A sequence of air frames with the same strafe direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strafe {
+    pub direction: StrafeDirection,
+    pub start_time: f32,
+    pub end_time: f32,
+    pub frames: usize,
+    /// The fraction of the frames in which the view turned in the strafe direction.
+    pub sync: f32,
+    /// The change of the horizontal speed during the strafe.
+    pub gain: f32,
+    /// The average difference between the angle of the movement direction to the velocity and
+    /// the optimal angle, in degrees. It's positive when the angle is too wide.
+    pub angle_deviation: f32,
+}
+
+/// The results of the analysis.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Analysis {
+    pub jumps: Vec<Jump>,
+    /// The largest number of consecutive bunnyhops.
+    pub max_bunnyhops: usize,
+    pub strafes: Vec<Strafe>,
+    /// The fraction of all strafe frames in which the view turned in the strafe direction.
+    pub sync: f32,
+}
+
+/// The state of a single engine frame.
+struct Sample {
+    time: f32,
+    frame: i32,
+    frametime: f32,
+    velocity: [f32; 3],
+    onground: bool,
+    jump: bool,
+    yaw: f32,
+    forwardmove: f32,
+    sidemove: f32,
+    maxspeed: f32,
+    airaccelerate: f32,
+}
+
+impl Sample {
+    fn new(frame: &Frame, data: &NetMsgData) -> Self {
+        let info = &data.info;
+
+        Sample { time: frame.time,
+                 frame: frame.frame,
+                 frametime: info.ref_params.frametime,
+                 velocity: info.ref_params.simvel,
+                 onground: info.ref_params.onground != 0,
+                 jump: info.usercmd.buttons().contains(Buttons::JUMP),
+                 yaw: info.usercmd.viewangles[1],
+                 forwardmove: info.usercmd.forwardmove,
+                 sidemove: info.usercmd.sidemove,
+                 maxspeed: info.movevars.maxspeed,
+                 airaccelerate: info.movevars.airaccelerate }
+    }
+
+    #[inline]
+    fn speed(&self) -> f32 {
+        self.velocity[0].hypot(self.velocity[1])
+    }
+}
+
+/// Normalizes an angle in degrees into the `[-180, 180)` range.
+fn normalize(angle: f32) -> f32 {
+    (angle + 180f32).rem_euclid(360f32) - 180f32
+}
+
+/// Returns the optimal angle between the velocity and the movement direction for gaining speed in
+/// the air, in degrees.
+///
+/// `wishspeed` is the movement speed before the air cap, limited by `maxspeed`.
+pub fn optimal_angle(speed: f32, wishspeed: f32, airaccelerate: f32, frametime: f32) -> f32 {
+    let accelspeed = airaccelerate * wishspeed * frametime;
+    let projection = AIR_WISHSPEED_CAP - accelspeed;
+
+    if projection <= 0f32 {
+        90f32
+    } else if speed <= projection {
+        0f32
+    } else {
+        (projection / speed).acos() * 180f32 / PI
+    }
+}
+
+/// Returns the angle between the velocity and the movement direction, and the optimal angle.
+fn angles(prev: &Sample, cur: &Sample) -> (f32, f32) {
+    // The right vector points 90 degrees clockwise from the view direction.
+    let wish_yaw = cur.yaw + (-cur.sidemove).atan2(cur.forwardmove) * 180f32 / PI;
+    let velocity_yaw = prev.velocity[1].atan2(prev.velocity[0]) * 180f32 / PI;
+    let angle = normalize(wish_yaw - velocity_yaw).abs();
+
+    let wishspeed = cur.forwardmove.hypot(cur.sidemove).min(cur.maxspeed);
+    let optimal = optimal_angle(prev.speed(), wishspeed, cur.airaccelerate, cur.frametime);
+
+    (angle, optimal)
+}
+
+/// Collects the samples, one for every engine frame.
+fn samples(entry: &DirectoryEntry) -> Vec<Sample> {
+    entry.engine_frames()
+         .filter_map(|engine_frame| engine_frame.netmsg())
+         .map(|(frame, data)| Sample::new(frame, data))
+         .collect()
+}
+
+fn jumps(samples: &[Sample]) -> Vec<Jump> {
+    let mut jumps = Vec::new();
+    let mut ground_frames = 0;
+    let mut was_in_air = false;
+    let mut bunnyhops = 0;
+
+    for pair in samples.windows(2) {
+        let (prev, cur) = (&pair[0], &pair[1]);
+
+        if prev.onground {
+            ground_frames += 1;
+
+            // Staying on the ground breaks the bunnyhop chain.
+            if ground_frames > MAX_BUNNYHOP_GROUND_FRAMES {
+                bunnyhops = 0;
+            }
+        } else {
+            ground_frames = 0;
+            was_in_air = true;
+        }
+
+        if prev.onground && !cur.onground && cur.jump && cur.velocity[2] > 0f32 {
+            if was_in_air && ground_frames <= MAX_BUNNYHOP_GROUND_FRAMES {
+                bunnyhops += 1;
+            }
+
+            jumps.push(Jump { time: cur.time,
+                              frame: cur.frame,
+                              speed: cur.speed(),
+                              ground_frames,
+                              bunnyhops });
+        }
+    }
+
+    jumps
+}
+
+/// A strafe being collected.
+struct StrafeBuilder {
+    strafe: Strafe,
+    start_speed: f32,
+    synced: usize,
+    deviation: f32,
+}
+
+impl StrafeBuilder {
+    fn new(direction: StrafeDirection, prev: &Sample) -> Self {
+        StrafeBuilder { strafe: Strafe { direction,
+                                         start_time: prev.time,
+                                         end_time: prev.time,
+                                         frames: 0,
+                                         sync: 0f32,
+                                         gain: 0f32,
+                                         angle_deviation: 0f32 },
+                        start_speed: prev.speed(),
+                        synced: 0,
+                        deviation: 0f32 }
+    }
+
+    fn add(&mut self, prev: &Sample, cur: &Sample) {
+        // Turning left increases the yaw.
+        let turn = normalize(cur.yaw - prev.yaw);
+        let synced = match self.strafe.direction {
+            StrafeDirection::Left => turn > 0f32,
+            StrafeDirection::Right => turn < 0f32,
+        };
+        if synced {
+            self.synced += 1;
+        }
+
+        let (angle, optimal) = angles(prev, cur);
+        self.deviation += angle - optimal;
+
+        self.strafe.end_time = cur.time;
+        self.strafe.frames += 1;
+        self.strafe.gain = cur.speed() - self.start_speed;
+    }
+
+    fn finish(self) -> Strafe {
+        let frames = self.strafe.frames as f32;
+        Strafe { sync: self.synced as f32 / frames,
+                 angle_deviation: self.deviation / frames,
+                 ..self.strafe }
+    }
+}
+
+fn strafes(samples: &[Sample]) -> Vec<Strafe> {
+    let mut strafes = Vec::new();
+    let mut current: Option<StrafeBuilder> = None;
+
+    for pair in samples.windows(2) {
+        let (prev, cur) = (&pair[0], &pair[1]);
+
+        let direction = if cur.onground || cur.frametime <= 0f32 {
+            None
+        } else if cur.sidemove < 0f32 {
+            Some(StrafeDirection::Left)
+        } else if cur.sidemove > 0f32 {
+            Some(StrafeDirection::Right)
+        } else {
+            None
+        };
+
+        if current.as_ref().map(|c| c.strafe.direction) != direction {
+            strafes.extend(current.take().map(StrafeBuilder::finish));
+        }
+
+        if let Some(direction) = direction {
+            current.get_or_insert_with(|| StrafeBuilder::new(direction, prev))
+                   .add(prev, cur);
+        }
+    }
+
+    strafes.extend(current.map(StrafeBuilder::finish));
+    strafes
+}
+
+/// Analyzes the movement in the directory entry.
+///
+/// Fails with `ErrorKind::HltvDemo` if the entry was recorded by an HLTV proxy.
+pub fn analyze(entry: &DirectoryEntry) -> Result<Analysis> {
+    if entry.is_hltv() {
+        bail!(ErrorKind::HltvDemo);
+    }
+
+    let samples = samples(entry);
+    let jumps = jumps(&samples);
+    let strafes = strafes(&samples);
+
+    let frames: usize = strafes.iter().map(|s| s.frames).sum();
+    let synced: f32 = strafes.iter().map(|s| s.sync * s.frames as f32).sum();
+
+    Ok(Analysis { max_bunnyhops: jumps.iter().map(|j| j.bunnyhops).max().unwrap_or(0),
+                  jumps,
+                  strafes,
+                  sync: if frames > 0 {
+                      synced / frames as f32
+                  } else {
+                      0f32
+                  } })
+}
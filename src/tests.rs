@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::thread;
+
 use super::*;
 
 #[test]
@@ -92,8 +96,8 @@ fn frame_types() {
 
     let mut command = [0; 64];
     command[..11].copy_from_slice(b"hello world");
-    assert!(if let FrameData::ConsoleCommand(ConsoleCommandData { command: c }) = frames[3].data {
-                c == &command[..]
+    assert!(if let FrameData::ConsoleCommand(ConsoleCommandData { command: ref c }) = frames[3].data {
+                **c == command[..]
             } else {
                 false
             });
@@ -194,3 +198,894 @@ fn error_invalid_netmsg_length() {
     assert_eq!(format!("{}", error_iter.next().unwrap()),
                format!("{}", parse::Error::InvalidNetMsgLength(16777215)));
 }
+
+#[test]
+fn write_round_trip() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let mut written = Vec::new();
+    demo.write(&mut written).unwrap();
+
+    assert_eq!(written.len(), bytes.len());
+    assert!(written[..] == bytes[..]);
+}
+
+#[test]
+fn write_round_trip_frame_types() {
+    let bytes = include_bytes!("../test-demos/frame-types.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let mut written = Vec::new();
+    demo.write(&mut written).unwrap();
+    let written_demo = Demo::parse(&written).unwrap();
+
+    // The file length in the test demo is wrong, the writer computes the correct one.
+    let entry = &written_demo.directory.entries[0];
+    assert_eq!(entry.offset, 544);
+    assert_eq!(entry.file_length, 1242);
+    assert_eq!(entry.frames, demo.directory.entries[0].frames);
+}
+
+#[test]
+fn write_error_field_too_long() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let mut demo = Demo::parse_without_frames(bytes).unwrap();
+    let map_name = [b'a'; 261];
+    demo.header.map_name = Cow::Borrowed(&map_name[..]);
+
+    let error = demo.write(Vec::new()).err().unwrap();
+    assert_eq!(format!("{}", error),
+               format!("{}", errors::ErrorKind::FieldTooLong("map_name", 261, 260)));
+}
+
+#[test]
+fn frame_iter() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+    let demo_without_frames = Demo::parse_without_frames(bytes).unwrap();
+
+    for (entry, entry_without_frames) in
+        demo.directory.entries.iter().zip(&demo_without_frames.directory.entries)
+    {
+        let frames = entry_without_frames.frame_iter(bytes)
+                                         .collect::<Result<Vec<_>, _>>()
+                                         .unwrap();
+        assert_eq!(frames, entry.frames);
+    }
+}
+
+#[test]
+fn frame_iter_error_invalid_frame_type() {
+    let bytes = include_bytes!("../test-demos/invalid-frame-type.dem");
+    let demo = Demo::parse_without_frames(bytes).unwrap();
+    let mut iter = demo.directory.entries[0].frame_iter(bytes);
+
+    let error = iter.find(|x| x.is_err()).unwrap().err().unwrap();
+    let mut error_iter = error.iter();
+
+    // Can't downcast errors. :(
+    assert_eq!(format!("{}", error_iter.next().unwrap()),
+               format!("{}", parse::Error::Frames));
+    assert_eq!(format!("{}", error_iter.next().unwrap()),
+               format!("{}", parse::Error::InvalidFrameType(10)));
+
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn engine_frames() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+    let entry = &demo.directory.entries[1];
+
+    let engine_frames = entry.engine_frames().collect::<Vec<_>>();
+    assert_eq!(engine_frames.iter().map(|f| f.frames.len()).sum::<usize>(),
+               entry.frames.len());
+    assert_eq!(engine_frames.iter().filter(|f| f.netmsg().is_some()).count(), 288);
+
+    for pair in engine_frames.windows(2) {
+        assert!(pair[0].frame() < pair[1].frame());
+    }
+
+    let (frame, _) = engine_frames[0].netmsg().unwrap();
+    assert_eq!(frame.frame, 0);
+    assert_eq!(frame.time, 0f32);
+}
+
+#[test]
+fn reader() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+    let mut reader = DemoReader::new(Cursor::new(&bytes[..])).unwrap();
+
+    assert_eq!(*reader.header(), demo.header);
+
+    assert_eq!(reader.directory().entries.len(), demo.directory.entries.len());
+
+    for (i, entry) in demo.directory.entries.iter().enumerate() {
+        assert_eq!(reader.directory().entries[i].offset, entry.offset);
+
+        let entry_frames = reader.read_entry(i).unwrap();
+        assert_eq!(entry_frames.parse().unwrap(), entry.frames);
+    }
+
+    assert_eq!(reader.read_demo().unwrap(), demo);
+}
+
+#[test]
+fn reader_wrong_file_length() {
+    // The entry file length is wrong in this demo, but the reader doesn't depend on it.
+    let bytes = include_bytes!("../test-demos/frame-types.dem");
+    let demo = Demo::parse(bytes).unwrap();
+    let mut reader = DemoReader::new(Cursor::new(&bytes[..])).unwrap();
+
+    assert_eq!(reader.read_entry(0).unwrap().parse().unwrap(),
+               demo.directory.entries[0].frames);
+}
+
+#[test]
+fn reader_error_invalid_directory_entry_count() {
+    let bytes = include_bytes!("../test-demos/invalid-directory-entry-count.dem");
+    let error = DemoReader::new(Cursor::new(&bytes[..])).err().unwrap();
+    let mut error_iter = error.iter();
+
+    // Can't downcast errors. :(
+    assert_eq!(format!("{}", error_iter.next().unwrap()),
+               format!("{}", parse::Error::Directory));
+    assert_eq!(format!("{}", error_iter.next().unwrap()),
+               format!("{}", parse::Error::InvalidDirectoryEntryCount(65535)));
+}
+
+#[test]
+fn reader_error_invalid_entry_index() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let mut reader = DemoReader::new(Cursor::new(&bytes[..])).unwrap();
+    let error = reader.read_entry(2).err().unwrap();
+    assert_eq!(format!("{}", error),
+               format!("{}", errors::ErrorKind::InvalidEntryIndex(2, 2)));
+}
+
+#[test]
+fn reader_error_invalid_netmsg_length() {
+    let bytes = include_bytes!("../test-demos/invalid-netmsg-length.dem");
+    let mut reader = DemoReader::new(Cursor::new(&bytes[..])).unwrap();
+    let error = reader.read_entry(0).err().unwrap();
+    let mut error_iter = error.iter();
+
+    // Can't downcast errors. :(
+    assert_eq!(format!("{}", error_iter.next().unwrap()),
+               format!("{}", parse::Error::Frames));
+    assert_eq!(format!("{}", error_iter.next().unwrap()),
+               format!("{}", parse::Error::InvalidNetMsgLength(16777215)));
+}
+
+#[test]
+fn into_owned() {
+    let demo = {
+        let bytes = include_bytes!("../test-demos/basic.dem").to_vec();
+        Demo::parse(&bytes).unwrap().into_owned()
+    };
+
+    thread::spawn(move || assert_eq!(demo.directory.entries[1].frames.len(), 911)).join()
+                                                                                  .unwrap();
+}
+
+#[test]
+fn netmsg_decode() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let mut decoder = netmsg::MessageDecoder::new(demo.header.net_protocol).unwrap();
+    let mut game_dir = None;
+
+    for entry in &demo.directory.entries {
+        for frame in &entry.frames {
+            if let FrameData::NetMsg((_, ref data)) = frame.data {
+                for message in decoder.decode(&data.msg).unwrap() {
+                    if let netmsg::Message::ServerInfo(info) = message {
+                        game_dir = Some(info.game_dir);
+                    }
+                }
+            }
+        }
+    }
+
+    assert_eq!(game_dir, Some(&b"valve"[..]));
+    assert_eq!(decoder.max_clients(), 1);
+    assert!(decoder.delta_description("entity_state_t").is_some());
+}
+
+#[test]
+fn netmsg_bit_reader_invalid_bit_count() {
+    let mut r = netmsg::BitReader::new(&[0xFF; 8]);
+
+    assert_eq!(r.read_bits(33), Err(netmsg::Error::InvalidBitCount(33)));
+    assert_eq!(r.read_signed_bits(0), Err(netmsg::Error::InvalidBitCount(0)));
+    assert_eq!(r.read_signed_bits(33), Err(netmsg::Error::InvalidBitCount(33)));
+    assert_eq!(r.position(), 0);
+
+    assert_eq!(r.read_bits(32), Ok(0xFFFF_FFFF));
+    assert_eq!(r.read_signed_bits(32), Ok(-0x7FFF_FFFF));
+}
+
+/// Writes the value into `count` bits, least significant bit first.
+fn write_bits(bits: &mut Vec<bool>, value: u32, count: u32) {
+    bits.extend((0..count).map(|i| value & (1 << i) != 0));
+}
+
+/// Returns the message with the given type and contents.
+fn bit_message(message_type: u8, bits: &[bool]) -> Vec<u8> {
+    let mut msg = vec![message_type];
+    msg.extend(bits.chunks(8).map(|byte| {
+                                      byte.iter()
+                                          .enumerate()
+                                          .fold(0u8, |acc, (i, &bit)| acc | ((bit as u8) << i))
+                                  }));
+    msg
+}
+
+#[test]
+fn netmsg_error_consistency_index_overflow() {
+    // No resources, followed by consistency indices which count up past `u16::MAX`.
+    let mut bits = Vec::new();
+    write_bits(&mut bits, 0, 12);
+    write_bits(&mut bits, 1, 1);
+    for _ in 0..(65536 / 31 + 1) {
+        write_bits(&mut bits, 0b11, 2);
+        write_bits(&mut bits, 31, 5);
+    }
+    write_bits(&mut bits, 0, 1);
+
+    let msg = bit_message(netmsg::svc::RESOURCELIST, &bits);
+    let mut decoder = netmsg::MessageDecoder::new(48).unwrap();
+    assert_eq!(decoder.decode(&msg).err(),
+               Some(netmsg::Error::InvalidConsistencyIndex));
+}
+
+#[test]
+fn netmsg_error_entity_index_overflow() {
+    // The entity count and the delta sequence, followed by a removal with the absolute index 2047
+    // and removals with relative indices which count up past `u16::MAX`.
+    let mut bits = Vec::new();
+    write_bits(&mut bits, 0, 16);
+    write_bits(&mut bits, 0, 8);
+    write_bits(&mut bits, 0b11, 2);
+    write_bits(&mut bits, 2047, 11);
+    for _ in 0..((65536 - 2047) / 63 + 1) {
+        write_bits(&mut bits, 0b01, 2);
+        write_bits(&mut bits, 63, 6);
+    }
+    write_bits(&mut bits, 0, 16);
+
+    let msg = bit_message(netmsg::svc::DELTAPACKETENTITIES, &bits);
+    let mut decoder = netmsg::MessageDecoder::new(48).unwrap();
+    assert_eq!(decoder.decode(&msg).err(),
+               Some(netmsg::Error::InvalidEntityIndex));
+}
+
+#[test]
+fn netmsg_state() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let mut tracker = netmsg::StateTracker::new(demo.header.net_protocol).unwrap();
+
+    for entry in &demo.directory.entries {
+        for frame in &entry.frames {
+            if let FrameData::NetMsg((_, ref data)) = frame.data {
+                tracker.update(data).unwrap();
+            }
+        }
+    }
+
+    assert_eq!(tracker.entities().len(), 23);
+    assert_eq!(tracker.entity(63).unwrap().origin(), [-34f32, 323f32, -204f32]);
+    assert_eq!(tracker.client_data().get("health"),
+               Some(&netmsg::DeltaValue::Float(100f32)));
+}
+
+#[test]
+fn netmsg_user_messages() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let mut decoder = netmsg::MessageDecoder::new(demo.header.net_protocol).unwrap();
+    let mut health = Vec::new();
+
+    for entry in &demo.directory.entries {
+        for frame in &entry.frames {
+            if let FrameData::NetMsg((_, ref data)) = frame.data {
+                for message in decoder.decode(&data.msg).unwrap() {
+                    if let netmsg::Message::UserMessage(message) = message {
+                        if let netmsg::TypedUserMessage::Health(x) = message.typed {
+                            health.push(x);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    assert_eq!(health, [100, 100]);
+    assert_eq!(decoder.game(), netmsg::Game::HalfLife);
+
+    let info = decoder.user_messages().by_name("Health").unwrap();
+    assert_eq!(info.size, Some(1));
+    assert_eq!(decoder.user_messages().get(info.index), Some(info));
+}
+
+#[test]
+fn netmsg_user_message_death_msg() {
+    use netmsg::user_messages::decode;
+    use netmsg::{Game, TypedUserMessage};
+
+    assert_eq!(decode("DeathMsg", b"\x01\x02crowbar\0", Game::HalfLife).unwrap(),
+               TypedUserMessage::DeathMsg { killer: 1,
+                                            victim: 2,
+                                            headshot: false,
+                                            weapon: b"crowbar" });
+    assert_eq!(decode("DeathMsg", b"\x01\x02\x01ak47\0", Game::CounterStrike).unwrap(),
+               TypedUserMessage::DeathMsg { killer: 1,
+                                            victim: 2,
+                                            headshot: true,
+                                            weapon: b"ak47" });
+    assert_eq!(decode("Unknown", b"\x01", Game::HalfLife).unwrap(),
+               TypedUserMessage::Raw);
+}
+
+#[test]
+fn error_position() {
+    let bytes = include_bytes!("../test-demos/invalid-frame-type.dem");
+    let error = Demo::parse(bytes).err().unwrap();
+
+    let demo = Demo::parse_without_frames(bytes).unwrap();
+    let position = error.position().unwrap();
+    assert_eq!(position,
+               errors::Position { offset: 544,
+                                  entry: Some(0),
+                                  frame: Some(0) });
+    assert_eq!(format!("{}", position),
+               "at offset 544 (0x220), directory entry 0, frame 0");
+
+    // The same frame fails to parse with every API.
+    let error = demo.directory.entries[0].frame_iter(bytes)
+                                         .for_entry(0)
+                                         .find_map(Result::err)
+                                         .unwrap();
+    assert_eq!(error.position(), Some(position));
+
+    let mut reader = DemoReader::new(Cursor::new(&bytes[..])).unwrap();
+    let error = reader.read_entry(0).err().unwrap();
+    assert_eq!(error.position(), Some(position));
+}
+
+#[test]
+fn error_position_second_entry() {
+    let mut bytes = include_bytes!("../test-demos/basic.dem").to_vec();
+    let offset = Demo::parse_without_frames(&bytes).unwrap().directory.entries[1].offset as usize;
+    bytes[offset] = 10;
+
+    let error = Demo::parse(&bytes).err().unwrap();
+    assert_eq!(error.position(),
+               Some(errors::Position { offset,
+                                       entry: Some(1),
+                                       frame: Some(0) }));
+    assert_eq!(format!("{}", error.iter().nth(1).unwrap()),
+               format!("{}", parse::Error::InvalidFrameType(10)));
+}
+
+#[test]
+fn error_position_directory() {
+    let bytes = include_bytes!("../test-demos/invalid-directory-entry-count.dem");
+    let error = Demo::parse(bytes).err().unwrap();
+    let position = error.position().unwrap();
+
+    let mut directory_offset = [0; 4];
+    directory_offset.copy_from_slice(&bytes[540..544]);
+    assert_eq!(position.offset, i32::from_le_bytes(directory_offset) as usize);
+    assert_eq!(position.entry, None);
+    assert_eq!(position.frame, None);
+
+    let error = DemoReader::new(Cursor::new(&bytes[..])).err().unwrap();
+    assert_eq!(error.position(), Some(position));
+}
+
+#[test]
+fn parse_lenient() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let (demo, diagnostics) = Demo::parse_lenient(bytes).unwrap();
+
+    assert_eq!(demo, Demo::parse(bytes).unwrap());
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn parse_lenient_truncated() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let original = Demo::parse(bytes).unwrap();
+
+    // Cut the demo in the middle of the second entry, losing the directory.
+    let bytes = &bytes[..100000];
+    assert!(Demo::parse(bytes).is_err());
+
+    let (demo, diagnostics) = Demo::parse_lenient(bytes).unwrap();
+    assert_eq!(demo.directory.entries.len(), 2);
+
+    let entry = &demo.directory.entries[0];
+    let original_entry = &original.directory.entries[0];
+    assert_eq!(entry.frames, original_entry.frames);
+    assert_eq!(entry.offset, original_entry.offset);
+    assert_eq!(entry.file_length, original_entry.file_length);
+    assert_eq!(entry.entry_type, original_entry.entry_type);
+
+    let frames = &demo.directory.entries[1].frames;
+    assert!(!frames.is_empty());
+    assert_eq!(frames[..], original.directory.entries[1].frames[..frames.len()]);
+
+    assert_eq!(diagnostics.len(), 2);
+    match diagnostics[0] {
+        Diagnostic::InvalidDirectory { .. } => {}
+        ref x => panic!("unexpected diagnostic: {}", x),
+    }
+    match diagnostics[1] {
+        Diagnostic::InvalidFrame { ref position, .. } => {
+            assert_eq!(position.entry, Some(1));
+            assert_eq!(position.frame, Some(frames.len()));
+        }
+        ref x => panic!("unexpected diagnostic: {}", x),
+    }
+}
+
+#[test]
+fn parse_lenient_invalid_frame_type() {
+    let bytes = include_bytes!("../test-demos/invalid-frame-type.dem");
+    let (demo, diagnostics) = Demo::parse_lenient(bytes).unwrap();
+
+    assert!(demo.directory.entries[0].frames.is_empty());
+    assert_eq!(diagnostics,
+               [Diagnostic::InvalidFrame { position: errors::Position { offset: 544,
+                                                                        entry: Some(0),
+                                                                        frame: Some(0) },
+                                           message: format!("{}: {}",
+                                                            parse::Error::Frames,
+                                                            parse::Error::InvalidFrameType(10)) }]);
+}
+
+#[test]
+fn directory_from_frames() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let frames = demo.directory.entries.iter().flat_map(|e| e.frames.iter().cloned());
+    let directory = Directory::from_frames(frames);
+
+    assert_eq!(directory.entries.len(), demo.directory.entries.len());
+    for (entry, original) in directory.entries.iter().zip(&demo.directory.entries) {
+        assert_eq!(entry.entry_type, original.entry_type);
+        assert_eq!(entry.cd_track, original.cd_track);
+        assert_eq!(entry.track_time, original.track_time);
+        assert_eq!(entry.frame_count, original.frame_count);
+        assert_eq!(entry.offset, original.offset);
+        assert_eq!(entry.file_length, original.file_length);
+        assert_eq!(entry.frames, original.frames);
+    }
+}
+
+#[test]
+fn repair() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let original = Demo::parse(bytes).unwrap();
+
+    let (demo, diagnostics) = Demo::repair(&bytes[..100000]).unwrap();
+    assert_eq!(diagnostics.len(), 2);
+
+    let mut fixed = Vec::new();
+    demo.write(&mut fixed).unwrap();
+    let fixed = Demo::parse(&fixed).unwrap();
+
+    assert_eq!(fixed.directory.entries.len(), 2);
+    assert_eq!(fixed.directory.entries[0], original.directory.entries[0]);
+
+    let entry = &fixed.directory.entries[1];
+    assert_eq!(entry.frames.last().unwrap().data, FrameData::NextSection);
+    assert_eq!(entry.frame_count, entry.frames.last().unwrap().frame + 1);
+
+    let error = Demo::repair(&bytes[..parse::header::HEADER_SIZE]).err().unwrap();
+    assert_eq!(format!("{}", error), format!("{}", errors::ErrorKind::NoFrames));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let json = serde_json::to_string(&demo).unwrap();
+    assert!(json.contains(r#""map_name":"c1a0""#));
+    assert!(json.contains(r#""description":"LOADING""#));
+
+    let deserialized: Demo = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.header.map_name, &b"c1a0"[..]);
+    assert_eq!(deserialized.header.game_dir, &b"valve"[..]);
+    assert_eq!(deserialized.directory.entries.len(), 2);
+    assert_eq!(deserialized.directory.entries[1].frames.len(), 911);
+    assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+}
+
+#[test]
+fn trim() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let clip = demo.trim_time(1f32, 2f32).unwrap();
+    let mut written = Vec::new();
+    clip.write(&mut written).unwrap();
+    let clip = Demo::parse(&written).unwrap();
+
+    assert_eq!(clip.directory.entries.len(), 2);
+    assert_eq!(clip.directory.entries[0], demo.directory.entries[0]);
+
+    let frames = &clip.directory.entries[1].frames;
+    assert_eq!(frames[0].data, FrameData::DemoStart);
+    assert_eq!(frames[1].time, 0f32);
+    assert_eq!(frames[1].frame, 0);
+    assert_eq!(frames.last().unwrap().data, FrameData::NextSection);
+    assert!(frames.iter().all(|f| f.time <= 1f32));
+
+    let clip = demo.trim_frames(100, 199).unwrap();
+    assert_eq!(clip.directory.entries[1].frame_count, 100);
+
+    assert!(demo.trim_time(10f32, 20f32).is_err());
+
+    // The start frames are moved along with the rest when the demo doesn't start at zero.
+    let mut later = demo.clone();
+    for frame in &mut later.directory.entries[1].frames {
+        frame.time += 100f32;
+        frame.frame += 10000;
+    }
+
+    let clip = later.trim_time(101f32, 102f32).unwrap();
+    let entry = &clip.directory.entries[1];
+    assert_eq!(entry.frames[0].data, FrameData::DemoStart);
+    assert_eq!(entry.frames[0].time, 0f32);
+    assert_eq!(entry.frames[0].frame, 0);
+    assert!(entry.frames
+                 .windows(2)
+                 .all(|w| w[0].time <= w[1].time && w[0].frame <= w[1].frame));
+    assert!((entry.track_time - entry.frames.last().unwrap().time).abs() < 0.001);
+    assert_eq!(entry.frame_count, entry.frames.last().unwrap().frame + 1);
+}
+
+#[test]
+fn concat() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let merged = Demo::concat(vec![demo.clone(), demo.clone()]).unwrap();
+    let mut written = Vec::new();
+    merged.write(&mut written).unwrap();
+    let merged = Demo::parse(&written).unwrap();
+
+    assert_eq!(merged.directory.entries.len(), 4);
+    assert_eq!(merged.directory.entries[2].frames, demo.directory.entries[0].frames);
+
+    let first = &merged.directory.entries[1];
+    let second = &merged.directory.entries[3];
+    assert_eq!(second.frames.len(), first.frames.len());
+    assert_eq!(second.frames[0].data, FrameData::DemoStart);
+    assert_eq!(second.frames[0].time, first.frames.last().unwrap().time);
+    assert_eq!(second.frames[0].frame, first.frames.last().unwrap().frame + 1);
+
+    let mut other = demo.clone();
+    other.header.net_protocol = 47;
+    assert!(Demo::concat(vec![demo.clone(), other]).is_err());
+
+    let mut other = demo.clone();
+    other.header.game_dir = Cow::Borrowed(b"cstrike");
+    assert!(Demo::concat(vec![demo.clone(), other]).is_err());
+
+    assert!(Demo::concat(vec![]).is_err());
+}
+
+#[test]
+fn timing() {
+    use timing::*;
+
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let timing = time(vec![&demo], Config::default());
+    assert_eq!(timing.splits.len(), 1);
+    assert_eq!(timing.splits[0].map, "c1a0");
+    assert_eq!(timing.splits[0].frames, 288);
+    assert!((timing.total - 2.886).abs() < 0.001);
+
+    // Consecutive demos on the same map make up a single split.
+    let config = Config { source: TimeSource::Msec,
+                          ..Config::default() };
+    let timing = time(&[demo.clone(), demo.clone()], config);
+    assert_eq!(timing.splits.len(), 1);
+    assert_eq!(timing.splits[0].frames, 576);
+
+    let config = Config { start: Some(Trigger::ConsoleCommand("-attack".to_string())),
+                          end: Some(Trigger::Map("c1a0".to_string())),
+                          ..Config::default() };
+    let timing = time(&[demo.clone(), demo.clone()], config.clone());
+    assert_eq!(timing.splits[0].frames, 2 * 288 - 21);
+
+    let mut other = demo.clone();
+    other.header.map_name = Cow::Borrowed(b"c1a0d");
+    let timing = time(&[demo.clone(), other.clone(), demo.clone()], config);
+    assert_eq!(timing.splits.len(), 2);
+    assert_eq!(timing.splits[1].map, "c1a0d");
+    assert_eq!(timing.splits[1].frames, 288);
+}
+
+#[test]
+fn trajectory() {
+    use trajectory::*;
+
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let trajectories = trajectories(&demo).unwrap();
+    assert_eq!(trajectories.len(), 1);
+
+    let trajectory = &trajectories[0];
+    assert_eq!(trajectory.entry, 1);
+    assert_eq!(trajectory.points.len(), 288);
+    assert_eq!(trajectory.start_time(), Some(0f32));
+    assert_eq!(trajectory.points[0].origin, [484f32, 318f32, -203.96875f32]);
+    assert!(!trajectory.points[0].ducking);
+
+    assert_eq!(trajectory.at(-1f32), None);
+    assert_eq!(trajectory.at(10f32), None);
+    assert_eq!(trajectory.at(0f32), Some(trajectory.points[0]));
+
+    let (a, b) = (trajectory.points[100], trajectory.points[101]);
+    let point = trajectory.at((a.time + b.time) / 2f32).unwrap();
+    assert_eq!(point.frame, a.frame);
+    for i in 0..3 {
+        assert!((point.origin[i] - (a.origin[i] + b.origin[i]) / 2f32).abs() < 0.001);
+    }
+}
+
+#[test]
+fn strafe() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let analysis = strafe::analyze(&demo.directory.entries[1]).unwrap();
+    assert_eq!(analysis.jumps.len(), 1);
+    assert_eq!(analysis.jumps[0].frame, 100);
+    assert_eq!(analysis.jumps[0].bunnyhops, 0);
+    assert_eq!(analysis.max_bunnyhops, 0);
+
+    assert_eq!(analysis.strafes.len(), 1);
+    let strafe = &analysis.strafes[0];
+    assert_eq!(strafe.direction, strafe::StrafeDirection::Right);
+    assert_eq!(strafe.frames, 30);
+    assert_eq!(strafe.sync, 5f32 / 30f32);
+    assert_eq!(analysis.sync, strafe.sync);
+
+    assert_eq!(strafe::optimal_angle(300f32, 320f32, 10f32, 0.01), 90f32);
+    assert_eq!(strafe::optimal_angle(20f32, 320f32, 10f32, 0.001), 0f32);
+    assert!((strafe::optimal_angle(300f32, 320f32, 10f32, 0.001) - 84.876).abs() < 0.01);
+}
+
+#[test]
+fn button_events() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let events = input::button_events(&demo.directory.entries[1]);
+    let jumps: Vec<_> = events.iter().filter(|e| e.button == Buttons::JUMP).collect();
+    assert_eq!(jumps.len(), 2);
+    assert!(jumps[0].pressed);
+    assert!(!jumps[1].pressed);
+    assert!(jumps[0].frame <= 100 && jumps[1].frame > 100);
+
+    let mut tracker = input::ButtonTracker::new();
+    assert!(tracker.update(0f32, 0, Buttons::empty()).is_empty());
+
+    let events = tracker.update(0.01, 1, Buttons::JUMP | Buttons::DUCK);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].button, Buttons::JUMP);
+    assert_eq!(events[1].button, Buttons::DUCK);
+    assert!(events.iter().all(|e| e.pressed));
+
+    let events = tracker.update(0.02, 2, Buttons::DUCK);
+    assert_eq!(events,
+               vec![input::ButtonEvent { time: 0.02,
+                                         frame: 2,
+                                         button: Buttons::JUMP,
+                                         pressed: false }]);
+    assert_eq!(tracker.buttons(), Buttons::DUCK);
+}
+
+#[test]
+fn hltas() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let mut demo = Demo::parse(bytes).unwrap();
+
+    let lines = hltas::frame_lines(&demo).unwrap();
+    assert_eq!(lines.len(), 288);
+    assert_eq!(lines.iter().map(|l| l.repeats).sum::<u32>(), 288);
+    assert_eq!(lines[0].frametime, 0.010001718);
+    assert_eq!(lines[0].yaw, 180f32);
+
+    let jumps = lines.iter().filter(|l| l.actions[0]).count();
+    assert!(jumps > 0);
+
+    let command = ConsoleCommandData { command: Cow::Borrowed(b"echo hi\0") };
+    demo.directory.entries[1].frames.insert(3,
+                                            Frame { time: 0f32,
+                                                    frame: 0,
+                                                    data: FrameData::ConsoleCommand(command) });
+
+    let mut script = Vec::new();
+    hltas::write(&demo, &mut script).unwrap();
+    let script = String::from_utf8(script).unwrap();
+    let mut lines = script.lines();
+    assert_eq!(lines.next(), Some("version 1"));
+    assert_eq!(lines.next(), Some("frames"));
+    assert_eq!(lines.next(),
+               Some("----------|------|------|0.010001718|180|0|1|echo hi"));
+}
+
+#[test]
+fn demo_builder() {
+    use parse::frame::netmsg::MAX_MESSAGE_LENGTH;
+
+    let msg = vec![0xAB; MAX_MESSAGE_LENGTH as usize];
+    let netmsg = NetMsgData { msg: Cow::Borrowed(&msg),
+                              ..NetMsgData::default() };
+
+    let mut builder = DemoBuilder::new(&b"c1a0"[..], &b"valve"[..]);
+    builder.loading()
+           .playback()
+           .demo_start()
+           .advance(0.01)
+           .demo_buffer(&b"buffer"[..])
+           .client_data(ClientDataData { fov: 90f32,
+                                         ..ClientDataData::default() })
+           .netmsg(NetMsgFrameType::Normal, netmsg.clone())
+           .console_command(&b"+jump"[..])
+           .event(EventData::default())
+           .weapon_anim(WeaponAnimData { anim: 1,
+                                         body: 2 })
+           .sound(SoundData { sample: Cow::Borrowed(b"weapons/pl_gun3.wav"),
+                              ..SoundData::default() })
+           .playback();
+
+    let demo = builder.build();
+    assert_eq!(demo.directory.entries.len(), 3);
+    assert_eq!(demo.directory.entries[0].frames,
+               vec![Frame { time: 0f32,
+                            frame: 0,
+                            data: FrameData::NextSection }]);
+    assert_eq!(demo.directory.entries[1].frames.len(), 9);
+    assert_eq!(demo.directory.entries[1].frame_count, 2);
+
+    let mut bytes = Vec::new();
+    builder.write(&mut bytes).unwrap();
+    let parsed = Demo::parse(&bytes).unwrap();
+
+    // Fixed-size fields are written padded with null bytes, so the parsed demo is compared by
+    // writing it again.
+    let mut rewritten = Vec::new();
+    parsed.write(&mut rewritten).unwrap();
+    assert_eq!(rewritten, bytes);
+
+    assert_eq!(parsed.directory.entries.len(), 3);
+    for (entry, built) in parsed.directory.entries.iter().zip(&demo.directory.entries) {
+        assert_eq!(entry.offset, built.offset);
+        assert_eq!(entry.file_length, built.file_length);
+        assert_eq!(entry.frames.len(), built.frames.len());
+    }
+
+    let frames = &parsed.directory.entries[1].frames;
+    assert_eq!(frames[2].data, demo.directory.entries[1].frames[2].data);
+    match frames[3].data {
+        FrameData::NetMsg((NetMsgFrameType::Normal, ref data)) => assert_eq!(data.msg, msg),
+        ref data => panic!("expected a NetMsg frame, got {:?}", data),
+    }
+    assert_eq!(frames[6].data, demo.directory.entries[1].frames[6].data);
+    assert_eq!(frames[7].data, demo.directory.entries[1].frames[7].data);
+    assert_eq!(parsed.directory.entries[2].frames[0].data, FrameData::NextSection);
+
+    let too_long = vec![0; MAX_MESSAGE_LENGTH as usize + 1];
+    builder.netmsg(NetMsgFrameType::Normal,
+                   NetMsgData { msg: Cow::Borrowed(&too_long),
+                                ..netmsg });
+    assert!(builder.write(&mut Vec::new()).is_err());
+    assert!(DemoBuilder::new(&b""[..], &b""[..]).write(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn hltv() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let mut demo = Demo::parse(bytes).unwrap();
+    assert!(!demo.is_hltv());
+
+    let players = hltv::player_trajectories(&demo).unwrap();
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0].player, 1);
+    assert_eq!(players[0].trajectory.entry, 1);
+    assert_eq!(players[0].trajectory.points.len(), 288);
+    assert_eq!(players[0].trajectory.points[0].viewangles, [0f32, 180f32, 0f32]);
+
+    for frame in &mut demo.directory.entries[1].frames {
+        if let FrameData::NetMsg((_, ref mut data)) = frame.data {
+            data.info.ref_params.spectator = 1;
+        }
+    }
+    assert!(demo.is_hltv());
+    assert!(demo.directory.entries[1].is_hltv());
+    assert!(!demo.directory.entries[0].is_hltv());
+
+    let hltv_demo = format!("{}", errors::ErrorKind::HltvDemo);
+    assert_eq!(format!("{}", trajectory::trajectories(&demo).unwrap_err()), hltv_demo);
+    assert_eq!(format!("{}", strafe::analyze(&demo.directory.entries[1]).unwrap_err()),
+               hltv_demo);
+    assert_eq!(format!("{}", hltas::frame_lines(&demo).unwrap_err()), hltv_demo);
+    assert_eq!(format!("{}", hltas::write(&demo, Vec::new()).unwrap_err()), hltv_demo);
+}
+
+#[test]
+fn probe() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+
+    let probe = super::probe(bytes);
+    assert_eq!(probe.magic, parse::header::MAGIC);
+    assert_eq!(probe.dialect, Dialect::GoldSrc);
+    assert_eq!(probe.demo_protocol, Some(5));
+    assert_eq!(probe.net_protocol, Some(48));
+    assert_eq!(probe.map_name, Some("c1a0".to_string()));
+    assert_eq!(probe.game_dir, Some("valve".to_string()));
+    let demo = Demo::parse_without_frames(bytes).unwrap();
+    assert_eq!(probe.playback_duration, Some(demo.directory.entries[1].track_time));
+    assert!(!probe.truncated);
+
+    let probe = super::probe(&bytes[..100000]);
+    assert_eq!(probe.map_name, Some("c1a0".to_string()));
+    assert_eq!(probe.playback_duration, None);
+    assert!(probe.truncated);
+
+    let probe = super::probe(include_bytes!("../test-demos/invalid-demo-protocol.dem"));
+    assert_eq!(probe.demo_protocol, Some(4));
+    assert_eq!(probe.map_name, None);
+
+    let probe = super::probe(b"IDEM\x03\0\0\0");
+    assert_eq!(probe.dialect, Dialect::Xash3D);
+    assert_eq!(probe.demo_protocol, None);
+
+    // Must not panic on any input.
+    for len in 0..2000 {
+        super::probe(&bytes[..len]);
+    }
+    let mut garbage = bytes[..2000].to_vec();
+    for (i, x) in garbage.iter_mut().enumerate().skip(8) {
+        *x = (i * 7919 % 256) as u8;
+    }
+    assert_eq!(super::probe(&garbage).dialect, Dialect::GoldSrc);
+    assert_eq!(super::probe(&bytes[8..]).dialect, Dialect::Unknown);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parse_parallel() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    assert_eq!(Demo::parse_parallel(bytes).unwrap(), Demo::parse(bytes).unwrap());
+
+    let bytes = include_bytes!("../test-demos/frame-types.dem");
+    assert_eq!(Demo::parse_parallel(bytes).unwrap(), Demo::parse(bytes).unwrap());
+
+    let bytes = include_bytes!("../test-demos/invalid-netmsg-length.dem");
+    let error = Demo::parse_parallel(bytes).err().unwrap();
+    let expected = Demo::parse(bytes).err().unwrap();
+    assert_eq!(format!("{}", error.iter().last().unwrap()),
+               format!("{}", expected.iter().last().unwrap()));
+    assert_eq!(error.position(), expected.position());
+}
@@ -0,0 +1,219 @@
+//! Speedrun timing across demos.
+//!
+//! A run is usually recorded as several demos, one for every map or after every load. `Timer`
+//! walks the playback frames of the demos in order and sums up the game time, excluding the
+//! loading segments. The time is split by map, and the timer can be started and stopped by
+//! triggers, such as a console command or reaching a map.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! use hldemo::timing::{Config, Timer, Trigger};
+//!
+//! let config = Config { end: Some(Trigger::ConsoleCommand("bxt_timer_stop".to_string())),
+//!                       ..Config::default() };
+//! let mut timer = Timer::new(config);
+//!
+//! for filename in &["c1a0.dem", "c1a0d.dem", "c1a0a.dem"] {
+//!     let mut bytes = Vec::new();
+//!     File::open(filename)?.read_to_end(&mut bytes)?;
+//!
+//!     let demo = hldemo::Demo::parse(&bytes)?;
+//!     timer.add_demo(&demo);
+//! }
+//!
+//! let timing = timer.finish();
+//! for split in &timing.splits {
+//!     println!("{}: {:.3}", split.map, split.time);
+//! }
+//! println!("Total: {:.3}", timing.total);
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use byte_string::lossy_string;
+use parse::directory::ENTRY_TYPE_LOADING;
+use types::*;
+
+/// Where the frame time is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeSource {
+    /// `RefParams::frametime`, the frame time the client used.
+    FrameTime,
+    /// `UserCmd::msec`, the frame time the client sent to the server, in whole milliseconds.
+    Msec,
+}
+
+/// An event which starts or stops the timer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    /// A `ConsoleCommand` frame with the given command.
+    ConsoleCommand(String),
+    /// The first playback frame on the given map, when the map differs from the previous demo's.
+    Map(String),
+}
+
+/// The timer configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Config {
+    pub source: TimeSource,
+    /// Starts the timer, `None` to start at the first playback frame.
+    pub start: Option<Trigger>,
+    /// Stops the timer, `None` to stop at the last playback frame.
+    pub end: Option<Trigger>,
+}
+
+/// The time spent on a map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Split {
+    pub map: String,
+    /// The time in seconds.
+    pub time: f64,
+    /// The number of timed frames.
+    pub frames: usize,
+}
+
+/// The result of timing a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    /// The total time in seconds.
+    pub total: f64,
+    /// The timed splits, in the order the maps were played. Consecutive demos on the same map
+    /// make up a single split.
+    pub splits: Vec<Split>,
+}
+
+/// Times a run recorded in one or more demos.
+///
+/// See the module documentation for details.
+#[derive(Debug, Clone)]
+pub struct Timer {
+    config: Config,
+    running: bool,
+    finished: bool,
+    map: Option<String>,
+    splits: Vec<Split>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { source: TimeSource::FrameTime,
+                 start: None,
+                 end: None }
+    }
+}
+
+impl Timer {
+    /// Creates a timer with the given configuration.
+    pub fn new(config: Config) -> Self {
+        Timer { running: config.start.is_none(),
+                config,
+                finished: false,
+                map: None,
+                splits: Vec::new() }
+    }
+
+    /// Times the playback frames of the demo.
+    ///
+    /// Demos must be added in the order they were recorded.
+    pub fn add_demo(&mut self, demo: &Demo) {
+        let map = lossy_string(&demo.header.map_name);
+
+        if self.map.as_ref() != Some(&map) {
+            self.map = Some(map.clone());
+            self.splits.push(Split { map: map.clone(),
+                                     time: 0f64,
+                                     frames: 0 });
+
+            let trigger = Trigger::Map(map);
+            self.trigger(&trigger);
+        }
+
+        for entry in &demo.directory.entries {
+            if entry.entry_type == ENTRY_TYPE_LOADING {
+                continue;
+            }
+
+            for engine_frame in entry.engine_frames() {
+                // The console commands are recorded before the NetMsg frames of the engine frame.
+                for frame in engine_frame.frames {
+                    if let FrameData::ConsoleCommand(ref data) = frame.data {
+                        let trigger = Trigger::ConsoleCommand(lossy_string(&data.command));
+                        self.trigger(&trigger);
+                    }
+                }
+
+                if let Some((_, data)) = engine_frame.netmsg() {
+                    if self.running {
+                        let time = match self.config.source {
+                            TimeSource::FrameTime => f64::from(data.info.ref_params.frametime),
+                            TimeSource::Msec => f64::from(data.info.usercmd.msec) / 1000f64,
+                        };
+
+                        let split = self.splits.last_mut().unwrap();
+                        split.time += time;
+                        split.frames += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the timer is running.
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Returns `true` if the end trigger was hit.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the total time and the splits.
+    ///
+    /// Maps on which the timer wasn't running are left out of the splits.
+    pub fn finish(self) -> Timing {
+        let splits: Vec<_> = self.splits.into_iter().filter(|s| s.frames > 0).collect();
+        Timing { total: splits.iter().map(|s| s.time).sum(),
+                 splits }
+    }
+
+    fn trigger(&mut self, trigger: &Trigger) {
+        if self.finished {
+            return;
+        }
+
+        if self.running {
+            if self.config.end.as_ref() == Some(trigger) {
+                self.running = false;
+                self.finished = true;
+            }
+        } else if self.config.start.as_ref() == Some(trigger) {
+            self.running = true;
+        }
+    }
+}
+
+/// Times the demos with the given configuration.
+pub fn time<'a, I: IntoIterator<Item = &'a Demo<'a>>>(demos: I, config: Config) -> Timing {
+    let mut timer = Timer::new(config);
+    for demo in demos {
+        timer.add_demo(demo);
+    }
+    timer.finish()
+}
@@ -0,0 +1,204 @@
+//! Player movement trajectories.
+//!
+//! The player state is stored in the `RefParams` of every NetMsg frame. `Trajectory` collects it
+//! for every engine frame of a playback segment and interpolates it between the frames.
+//!
+//! In HLTV demos `RefParams` holds the spectator camera, so `ErrorKind::HltvDemo` is returned for
+//! them and the player trajectories should be taken from the entity states with
+//! `hltv::player_trajectories()` instead.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! #
+//! # fn try_main() -> Result<(), Box<Error>> {
+//! extern crate hldemo;
+//!
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! let mut bytes = Vec::new();
+//! let mut f = File::open("demo.dem")?;
+//! f.read_to_end(&mut bytes);
+//!
+//! let demo = hldemo::Demo::parse(&bytes)?;
+//! for trajectory in hldemo::trajectory::trajectories(&demo)? {
+//!     if let Some(point) = trajectory.at(1.5) {
+//!         println!("entry {}: {:?} at {} ups", trajectory.entry, point.origin, point.speed);
+//!     }
+//! }
+//! #
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() {
+//! #     try_main().unwrap();
+//! # }
+//! ```
+
+use errors::*;
+use parse::directory::ENTRY_TYPE_LOADING;
+use types::*;
+
+/// The view height of a standing player. It's lower when the player is ducking.
+pub const DEFAULT_VIEWHEIGHT: f32 = 28f32;
+
+/// The player state at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    /// The demo time.
+    pub time: f32,
+    /// The frame number, or the number of the preceding frame for interpolated points.
+    pub frame: i32,
+    pub origin: [f32; 3],
+    pub velocity: [f32; 3],
+    /// The horizontal speed.
+    pub speed: f32,
+    pub viewangles: [f32; 3],
+    pub onground: bool,
+    pub waterlevel: i32,
+    /// `true` if the player is ducked or in the process of ducking.
+    pub ducking: bool,
+}
+
+/// The player trajectory during a playback segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    /// The index of the directory entry.
+    pub entry: usize,
+    /// The points in the order of time, one for every engine frame.
+    pub points: Vec<Point>,
+}
+
+/// Returns the trajectories of all playback segments.
+///
+/// Fails with `ErrorKind::HltvDemo` for HLTV demos.
+pub fn trajectories(demo: &Demo) -> Result<Vec<Trajectory>> {
+    if demo.is_hltv() {
+        bail!(ErrorKind::HltvDemo);
+    }
+
+    demo.directory
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|&(_, entry)| entry.entry_type != ENTRY_TYPE_LOADING)
+        .map(|(i, entry)| Trajectory::from_entry(i, entry))
+        .collect()
+}
+
+#[inline]
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[inline]
+fn lerp_vector(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [lerp(a[0], b[0], t), lerp(a[1], b[1], t), lerp(a[2], b[2], t)]
+}
+
+/// Interpolates angles in degrees along the shortest arc.
+fn lerp_angles(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    let mut rv = [0f32; 3];
+    for i in 0..3 {
+        let mut delta = (b[i] - a[i]) % 360f32;
+        if delta > 180f32 {
+            delta -= 360f32;
+        } else if delta < -180f32 {
+            delta += 360f32;
+        }
+        rv[i] = a[i] + delta * t;
+    }
+    rv
+}
+
+#[inline]
+fn horizontal_speed(velocity: [f32; 3]) -> f32 {
+    velocity[0].hypot(velocity[1])
+}
+
+impl Point {
+    /// Creates a point from the state of a NetMsg frame.
+    pub fn from_netmsg(time: f32, frame: i32, data: &NetMsgData) -> Self {
+        let params = &data.info.ref_params;
+
+        Point { time,
+                frame,
+                origin: params.simorg,
+                velocity: params.simvel,
+                speed: horizontal_speed(params.simvel),
+                viewangles: params.viewangles,
+                onground: params.onground != 0,
+                waterlevel: params.waterlevel,
+                ducking: params.viewheight[2] < DEFAULT_VIEWHEIGHT }
+    }
+
+    /// Interpolates between this point and the next one.
+    ///
+    /// The discrete fields are taken from this point.
+    pub fn lerp(&self, next: &Point, time: f32) -> Self {
+        let t = if next.time > self.time {
+            (time - self.time) / (next.time - self.time)
+        } else {
+            0f32
+        };
+
+        let velocity = lerp_vector(self.velocity, next.velocity, t);
+
+        Point { time,
+                origin: lerp_vector(self.origin, next.origin, t),
+                velocity,
+                speed: horizontal_speed(velocity),
+                viewangles: lerp_angles(self.viewangles, next.viewangles, t),
+                ..*self }
+    }
+}
+
+impl Trajectory {
+    /// Collects the trajectory from the engine frames of the directory entry, see
+    /// `DirectoryEntry::engine_frames()`.
+    ///
+    /// Fails with `ErrorKind::HltvDemo` if the entry was recorded by an HLTV proxy.
+    pub fn from_entry(index: usize, entry: &DirectoryEntry) -> Result<Self> {
+        if entry.is_hltv() {
+            bail!(ErrorKind::HltvDemo);
+        }
+
+        let points = entry.engine_frames()
+                          .filter_map(|engine_frame| engine_frame.netmsg())
+                          .map(|(frame, data)| Point::from_netmsg(frame.time, frame.frame, data))
+                          .collect();
+
+        Ok(Trajectory { entry: index,
+                        points })
+    }
+
+    /// Returns the start time, or `None` if the trajectory is empty.
+    #[inline]
+    pub fn start_time(&self) -> Option<f32> {
+        self.points.first().map(|p| p.time)
+    }
+
+    /// Returns the end time, or `None` if the trajectory is empty.
+    #[inline]
+    pub fn end_time(&self) -> Option<f32> {
+        self.points.last().map(|p| p.time)
+    }
+
+    /// Returns the point at the given time, interpolated between the surrounding points.
+    ///
+    /// Returns `None` if the time is outside of the trajectory.
+    pub fn at(&self, time: f32) -> Option<Point> {
+        // The index of the first point after the time.
+        let next = self.points.partition_point(|p| p.time <= time);
+
+        if next == 0 {
+            None
+        } else if next < self.points.len() {
+            Some(self.points[next - 1].lerp(&self.points[next], time))
+        } else {
+            self.points.last().filter(|last| last.time == time).cloned()
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Cutting a range of frames out of a demo.
+
+use errors::*;
+use parse::directory::ENTRY_TYPE_LOADING;
+use types::*;
+
+/// Returns `true` if the frame is needed by the engine to start the playback.
+fn is_start_frame(frame: &Frame) -> bool {
+    matches!(frame.data,
+             FrameData::DemoStart | FrameData::NetMsg((NetMsgFrameType::Start, _)))
+}
+
+impl<'a> Demo<'a> {
+    /// Returns a demo containing only the playback frames between `start` and `end` seconds,
+    /// inclusive.
+    ///
+    /// The loading segment and the start frames of the playback segments (`DemoStart` and
+    /// `NetMsg` frames of type `Start`) are kept, so the engine can play the result. The kept
+    /// frames are moved so that the first one starts at zero time and frame, the start frames are
+    /// moved to the first kept frame of their segment, and the directory is rebuilt.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::{BufWriter, Read};
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut f = File::open("demo.dem")?;
+    /// f.read_to_end(&mut bytes);
+    ///
+    /// let demo = hldemo::Demo::parse(&bytes)?;
+    /// let clip = demo.trim_time(10f32, 20f32)?;
+    /// clip.write(BufWriter::new(File::create("clip.dem")?))?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn trim_time(&self, start: f32, end: f32) -> Result<Demo<'a>> {
+        self.trim(|frame| frame.time >= start && frame.time <= end)
+    }
+
+    /// Returns a demo containing only the playback frames with frame numbers between `start` and
+    /// `end`, inclusive.
+    ///
+    /// See `trim_time()` for details.
+    pub fn trim_frames(&self, start: i32, end: i32) -> Result<Demo<'a>> {
+        self.trim(|frame| frame.frame >= start && frame.frame <= end)
+    }
+
+    fn trim<F: Fn(&Frame) -> bool>(&self, in_range: F) -> Result<Demo<'a>> {
+        let is_playback = |entry: &DirectoryEntry| entry.entry_type != ENTRY_TYPE_LOADING;
+        let is_kept = |frame: &Frame| {
+            frame.data != FrameData::NextSection && !is_start_frame(frame) && in_range(frame)
+        };
+
+        let (time, frame) = match self.directory
+                                      .entries
+                                      .iter()
+                                      .filter(|entry| is_playback(entry))
+                                      .flat_map(|entry| &entry.frames)
+                                      .find(|frame| is_kept(frame))
+        {
+            Some(first) => (first.time, first.frame),
+            None => bail!(ErrorKind::EmptyRange),
+        };
+
+        let entries = self.directory.entries.iter().filter_map(|entry| {
+            if !is_playback(entry) {
+                return Some(entry.clone());
+            }
+
+            // Playback segments entirely outside of the range are removed.
+            let first = entry.frames.iter().find(|f| is_kept(f))?;
+
+            let frames = entry.frames.iter().filter_map(|f| {
+                if is_start_frame(f) {
+                    Some(Frame { time: first.time - time,
+                                 frame: first.frame - frame,
+                                 data: f.data.clone() })
+                } else if is_kept(f) {
+                    Some(Frame { time: f.time - time,
+                                 frame: f.frame - frame,
+                                 data: f.data.clone() })
+                } else {
+                    None
+                }
+            });
+
+            Some(DirectoryEntry { frames: frames.collect(),
+                                  ..entry.clone_without_frames() })
+        });
+
+        let mut demo = Demo { header: self.header.clone(),
+                              directory: Directory { entries: entries.collect() } };
+        demo.rebuild_directory();
+        Ok(demo)
+    }
+}
+
+impl<'a> DirectoryEntry<'a> {
+    /// Returns a copy of the entry without the frames.
+    fn clone_without_frames(&self) -> Self {
+        DirectoryEntry { entry_type: self.entry_type,
+                         description: self.description.clone(),
+                         flags: self.flags,
+                         cd_track: self.cd_track,
+                         track_time: self.track_time,
+                         frame_count: self.frame_count,
+                         offset: self.offset,
+                         file_length: self.file_length,
+                         frames: Vec::new() }
+    }
+}
@@ -1,10 +1,27 @@
 //! Types for objects which demos consist of.
+//!
+//! Byte string fields are `Cow`s which borrow from the input when parsing. Every type with a
+//! lifetime has an `into_owned()` method which converts it into a `'static` version that owns all
+//! of its data.
+//!
+//! With the `serde` feature, these types implement `Serialize` and `Deserialize`. Null-padded
+//! byte strings like `Header::map_name` and `SoundData::sample` are serialized as strings trimmed
+//! at the first null byte, while binary data like `NetMsgData::msg` is serialized as bytes.
+
+use std::borrow::Cow;
+use std::io::Write;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use errors::*;
 use parse;
+use parse::directory::map_entries;
+use write;
 
 /// A Goldsource demo.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Demo<'a> {
     pub header: Header<'a>,
     pub directory: Directory<'a>,
@@ -38,7 +55,18 @@ impl<'a> Demo<'a> {
     /// # }
     /// ```
     pub fn parse(input: &[u8]) -> Result<Demo> {
-        parse::demo(input).map(|(_, demo)| demo).map_err(Into::into)
+        let mut demo = Demo::parse_without_frames(input)?;
+
+        // The frames are parsed with `FrameIter`, which keeps track of the error position.
+        let frames = map_entries(&demo.directory.entries, |i, entry| {
+            entry.frame_iter(input).for_entry(i).collect::<Result<Vec<_>>>()
+        });
+
+        for (entry, frames) in demo.directory.entries.iter_mut().zip(frames) {
+            entry.frames = frames?;
+        }
+
+        Ok(demo)
     }
 
     /// Parses a demo's header and directory, without parsing frame data.
@@ -72,29 +100,107 @@ impl<'a> Demo<'a> {
     /// ```
     pub fn parse_without_frames(input: &[u8]) -> Result<Demo> {
         parse::demo_without_frames(input).map(|(_, demo)| demo)
-                                         .map_err(Into::into)
+                                         .map_err(|err| {
+                                                      let position = error_position(&err, input);
+                                                      Error::from_nom_at(err, position)
+                                                  })
+    }
+
+    /// Writes the demo in the demo file format.
+    ///
+    /// The directory offset and the offsets and lengths of directory entries are computed from
+    /// the frames, so the values stored in the header and the directory are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::{BufWriter, Read};
+    ///
+    /// let mut bytes = Vec::new();
+    /// let mut f = File::open("demo.dem")?;
+    /// f.read_to_end(&mut bytes);
+    ///
+    /// let demo = hldemo::Demo::parse(&bytes)?;
+    /// demo.write(BufWriter::new(File::create("copy.dem")?))?;
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn write<W: Write>(&self, output: W) -> Result<()> {
+        write::demo(output, self)
+    }
+
+    /// Converts the demo into an owned version which doesn't borrow from the input.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// #
+    /// # fn try_main() -> Result<(), Box<Error>> {
+    /// extern crate hldemo;
+    ///
+    /// use std::fs::File;
+    /// use std::io::Read;
+    ///
+    /// let demo = {
+    ///     let mut bytes = Vec::new();
+    ///     let mut f = File::open("demo.dem")?;
+    ///     f.read_to_end(&mut bytes);
+    ///
+    ///     hldemo::Demo::parse(&bytes)?.into_owned()
+    /// };
+    ///
+    /// std::thread::spawn(move || println!("{}", demo.directory.entries.len()));
+    /// #
+    /// #     Ok(())
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #     try_main().unwrap();
+    /// # }
+    /// ```
+    pub fn into_owned(self) -> Demo<'static> {
+        Demo { header: self.header.into_owned(),
+               directory: self.directory.into_owned() }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Header<'a> {
     pub demo_protocol: i32,
     pub net_protocol: i32,
-    pub map_name: &'a [u8],
-    pub game_dir: &'a [u8],
+    #[cfg_attr(feature = "serde", serde(with = "::byte_string"))]
+    pub map_name: Cow<'a, [u8]>,
+    #[cfg_attr(feature = "serde", serde(with = "::byte_string"))]
+    pub game_dir: Cow<'a, [u8]>,
     pub map_crc: u32,
     pub directory_offset: i32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Directory<'a> {
     pub entries: Vec<DirectoryEntry<'a>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DirectoryEntry<'a> {
     pub entry_type: i32,
-    pub description: &'a [u8],
+    #[cfg_attr(feature = "serde", serde(with = "::byte_string"))]
+    pub description: Cow<'a, [u8]>,
     pub flags: i32,
     pub cd_track: i32,
     pub track_time: f32,
@@ -105,14 +211,16 @@ pub struct DirectoryEntry<'a> {
     pub frames: Vec<Frame<'a>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Frame<'a> {
     pub time: f32,
     pub frame: i32,
     pub data: FrameData<'a>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FrameData<'a> {
     NetMsg((NetMsgFrameType, NetMsgData<'a>)),
     DemoStart,
@@ -125,12 +233,15 @@ pub enum FrameData<'a> {
     DemoBuffer(DemoBufferData<'a>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ConsoleCommandData<'a> {
-    pub command: &'a [u8],
+    #[cfg_attr(feature = "serde", serde(with = "::byte_string"))]
+    pub command: Cow<'a, [u8]>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ClientDataData {
     pub origin: [f32; 3],
     pub viewangles: [f32; 3],
@@ -138,7 +249,8 @@ pub struct ClientDataData {
     pub fov: f32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EventData {
     pub flags: i32,
     pub index: i32,
@@ -146,7 +258,8 @@ pub struct EventData {
     pub args: EventArgs,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EventArgs {
     pub flags: i32,
     pub entity_index: i32,
@@ -162,28 +275,33 @@ pub struct EventArgs {
     pub bparam2: i32,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct WeaponAnimData {
     pub anim: i32,
     pub body: i32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SoundData<'a> {
     pub channel: i32,
-    pub sample: &'a [u8],
+    #[cfg_attr(feature = "serde", serde(with = "::byte_string"))]
+    pub sample: Cow<'a, [u8]>,
     pub attenuation: f32,
     pub volume: f32,
     pub flags: i32,
     pub pitch: i32,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DemoBufferData<'a> {
-    pub buffer: &'a [u8],
+    pub buffer: Cow<'a, [u8]>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NetMsgFrameType {
     /// Initialization frames.
     Start,
@@ -193,7 +311,8 @@ pub enum NetMsgFrameType {
     Unknown(u8),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NetMsgData<'a> {
     pub info: NetMsgInfo<'a>,
     pub incoming_sequence: i32,
@@ -203,10 +322,11 @@ pub struct NetMsgData<'a> {
     pub outgoing_sequence: i32,
     pub reliable_sequence: i32,
     pub last_reliable_sequence: i32,
-    pub msg: &'a [u8],
+    pub msg: Cow<'a, [u8]>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NetMsgInfo<'a> {
     pub timestamp: f32,
     pub ref_params: RefParams,
@@ -216,7 +336,8 @@ pub struct NetMsgInfo<'a> {
     pub viewmodel: i32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RefParams {
     pub vieworg: [f32; 3],
     pub viewangles: [f32; 3],
@@ -253,7 +374,8 @@ pub struct RefParams {
     pub only_client_draw: i32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct UserCmd {
     pub lerp_msec: i16,
     pub msec: u8,
@@ -262,6 +384,7 @@ pub struct UserCmd {
     pub sidemove: f32,
     pub upmove: f32,
     pub lightlevel: i8,
+    /// The raw button bits, see `buttons()` for the typed version.
     pub buttons: u16,
     pub impulse: i8,
     pub weaponselect: i8,
@@ -269,7 +392,31 @@ pub struct UserCmd {
     pub impact_position: [f32; 3],
 }
 
-#[derive(Debug, PartialEq)]
+bitflags! {
+    /// The buttons held by the player, as stored in `UserCmd::buttons`.
+    #[derive(Default)]
+    pub struct Buttons: u16 {
+        const ATTACK = 1;
+        const JUMP = 1 << 1;
+        const DUCK = 1 << 2;
+        const FORWARD = 1 << 3;
+        const BACK = 1 << 4;
+        const USE = 1 << 5;
+        const CANCEL = 1 << 6;
+        const LEFT = 1 << 7;
+        const RIGHT = 1 << 8;
+        const MOVELEFT = 1 << 9;
+        const MOVERIGHT = 1 << 10;
+        const ATTACK2 = 1 << 11;
+        const RUN = 1 << 12;
+        const RELOAD = 1 << 13;
+        const ALT1 = 1 << 14;
+        const SCORE = 1 << 15;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MoveVars<'a> {
     pub gravity: f32,
     pub stopspeed: f32,
@@ -288,7 +435,8 @@ pub struct MoveVars<'a> {
     pub zmax: f32,
     pub wave_height: f32,
     pub footsteps: i32,
-    pub sky_name: &'a [u8],
+    #[cfg_attr(feature = "serde", serde(with = "::byte_string"))]
+    pub sky_name: Cow<'a, [u8]>,
     pub rollangle: f32,
     pub rollspeed: f32,
     pub skycolor_r: f32,
@@ -299,6 +447,123 @@ pub struct MoveVars<'a> {
     pub skyvec_z: f32,
 }
 
+/// Returns the position of a nom error from parsing the demo header or directory.
+fn error_position(err: &::nom::Err<&[u8], parse::Error>, input: &[u8]) -> Position {
+    Position { offset: nom_offset(err, input),
+               entry: None,
+               frame: None }
+}
+
+impl<'a> Header<'a> {
+    /// Converts the header into an owned version.
+    pub fn into_owned(self) -> Header<'static> {
+        Header { map_name: Cow::Owned(self.map_name.into_owned()),
+                 game_dir: Cow::Owned(self.game_dir.into_owned()),
+                 ..self }
+    }
+}
+
+impl<'a> Directory<'a> {
+    /// Converts the directory into an owned version.
+    pub fn into_owned(self) -> Directory<'static> {
+        Directory { entries: self.entries
+                                 .into_iter()
+                                 .map(DirectoryEntry::into_owned)
+                                 .collect() }
+    }
+}
+
+impl<'a> DirectoryEntry<'a> {
+    /// Converts the directory entry into an owned version.
+    pub fn into_owned(self) -> DirectoryEntry<'static> {
+        DirectoryEntry { description: Cow::Owned(self.description.into_owned()),
+                         frames: self.frames.into_iter().map(Frame::into_owned).collect(),
+                         ..self }
+    }
+}
+
+impl<'a> Frame<'a> {
+    /// Converts the frame into an owned version.
+    pub fn into_owned(self) -> Frame<'static> {
+        Frame { data: self.data.into_owned(),
+                ..self }
+    }
+}
+
+impl<'a> FrameData<'a> {
+    /// Converts the frame data into an owned version.
+    pub fn into_owned(self) -> FrameData<'static> {
+        match self {
+            FrameData::NetMsg((frame_type, data)) => {
+                FrameData::NetMsg((frame_type, data.into_owned()))
+            }
+            FrameData::DemoStart => FrameData::DemoStart,
+            FrameData::ConsoleCommand(data) => FrameData::ConsoleCommand(data.into_owned()),
+            FrameData::ClientData(data) => FrameData::ClientData(data),
+            FrameData::NextSection => FrameData::NextSection,
+            FrameData::Event(data) => FrameData::Event(data),
+            FrameData::WeaponAnim(data) => FrameData::WeaponAnim(data),
+            FrameData::Sound(data) => FrameData::Sound(data.into_owned()),
+            FrameData::DemoBuffer(data) => FrameData::DemoBuffer(data.into_owned()),
+        }
+    }
+}
+
+impl<'a> ConsoleCommandData<'a> {
+    /// Converts the console command data into an owned version.
+    pub fn into_owned(self) -> ConsoleCommandData<'static> {
+        ConsoleCommandData { command: Cow::Owned(self.command.into_owned()) }
+    }
+}
+
+impl<'a> SoundData<'a> {
+    /// Converts the sound data into an owned version.
+    pub fn into_owned(self) -> SoundData<'static> {
+        SoundData { sample: Cow::Owned(self.sample.into_owned()),
+                    ..self }
+    }
+}
+
+impl<'a> DemoBufferData<'a> {
+    /// Converts the demo buffer data into an owned version.
+    pub fn into_owned(self) -> DemoBufferData<'static> {
+        DemoBufferData { buffer: Cow::Owned(self.buffer.into_owned()) }
+    }
+}
+
+impl<'a> NetMsgData<'a> {
+    /// Converts the NetMsg data into an owned version.
+    pub fn into_owned(self) -> NetMsgData<'static> {
+        NetMsgData { info: self.info.into_owned(),
+                     msg: Cow::Owned(self.msg.into_owned()),
+                     ..self }
+    }
+}
+
+impl<'a> NetMsgInfo<'a> {
+    /// Converts the NetMsg info into an owned version.
+    pub fn into_owned(self) -> NetMsgInfo<'static> {
+        NetMsgInfo { movevars: self.movevars.into_owned(),
+                     ..self }
+    }
+}
+
+impl UserCmd {
+    /// Returns the buttons held by the player.
+    #[inline]
+    pub fn buttons(&self) -> Buttons {
+        Buttons::from_bits_truncate(self.buttons)
+    }
+}
+
+impl<'a> MoveVars<'a> {
+    /// Converts the movevars into an owned version.
+    pub fn into_owned(self) -> MoveVars<'static> {
+        MoveVars { sky_name: Cow::Owned(self.sky_name.into_owned()),
+                   ..self }
+    }
+}
+
 impl NetMsgFrameType {
     /// Converts a raw frame type into a `NetMsgFrameType`.
     ///
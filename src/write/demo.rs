@@ -0,0 +1,31 @@
+use std::io::Write;
+
+use super::directory::*;
+use super::frame::*;
+use super::header::*;
+use super::*;
+use parse::header::HEADER_SIZE;
+use types::*;
+
+/// Writes a demo.
+///
+/// The frames of every directory entry are written in order right after the header, followed by
+/// the directory. Every entry is expected to end with a `NextSection` frame, like entries of a
+/// parsed demo do.
+pub fn demo<W: Write>(mut w: W, demo: &Demo) -> Result<()> {
+    let mut offset = HEADER_SIZE;
+    let mut layout = Vec::with_capacity(demo.directory.entries.len());
+    for entry in &demo.directory.entries {
+        let length = frames_size(&entry.frames);
+        layout.push((offset as i32, length as i32));
+        offset += length;
+    }
+
+    header(&mut w, &demo.header, offset as i32)?;
+
+    for entry in &demo.directory.entries {
+        frames(&mut w, &entry.frames)?;
+    }
+
+    directory(&mut w, &demo.directory, &layout)
+}
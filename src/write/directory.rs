@@ -0,0 +1,44 @@
+//! Demo directory writing functions.
+
+use std::io::Write;
+
+use super::*;
+use parse;
+use parse::directory::{MAX_ENTRY_COUNT, MIN_ENTRY_COUNT};
+use types::*;
+
+/// Writes a directory entry, using the given offset and file length.
+pub fn entry<W: Write>(w: &mut W,
+                       entry: &DirectoryEntry,
+                       offset: i32,
+                       file_length: i32)
+                       -> Result<()> {
+    i32(w, entry.entry_type)?;
+    fixed(w, "description", &entry.description, 64)?;
+    i32(w, entry.flags)?;
+    i32(w, entry.cd_track)?;
+    f32(w, entry.track_time)?;
+    i32(w, entry.frame_count)?;
+    i32(w, offset)?;
+    i32(w, file_length)
+}
+
+/// Writes a directory.
+///
+/// `layout` contains the offset and the file length for every entry.
+pub fn directory<W: Write>(w: &mut W,
+                           directory: &Directory,
+                           layout: &[(i32, i32)])
+                           -> Result<()> {
+    let count = directory.entries.len() as i32;
+    if !(MIN_ENTRY_COUNT..=MAX_ENTRY_COUNT).contains(&count) {
+        return Err(parse::Error::InvalidDirectoryEntryCount(count).into());
+    }
+
+    i32(w, count)?;
+    for (e, &(offset, file_length)) in directory.entries.iter().zip(layout) {
+        entry(w, e, offset, file_length)?;
+    }
+
+    Ok(())
+}
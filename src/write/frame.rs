@@ -0,0 +1,223 @@
+//! Demo frame writing functions.
+
+use std::io::Write;
+
+use super::*;
+use parse;
+use parse::frame::netmsg::MAX_MESSAGE_LENGTH;
+use parse::frame::{FrameType, FRAME_HEADER_SIZE};
+use types::*;
+
+/// Returns the size of the frame in bytes, including the frame header.
+pub fn frame_size(frame: &Frame) -> usize {
+    let raw = frame_type(&frame.data);
+    // Unknown NetMsg frame types are written as is.
+    let layout = FrameType::from_raw(raw).unwrap_or(FrameType::NetMsg(raw))
+                                         .data_layout();
+
+    let length = match frame.data {
+        FrameData::NetMsg((_, ref data)) => data.msg.len(),
+        FrameData::Sound(ref data) => data.sample.len(),
+        FrameData::DemoBuffer(ref data) => data.buffer.len(),
+        _ => 0,
+    };
+
+    FRAME_HEADER_SIZE + layout.fixed_size + length
+}
+
+/// Returns the total size of the frames in bytes.
+pub fn frames_size(frames: &[Frame]) -> usize {
+    frames.iter().map(frame_size).sum()
+}
+
+/// Returns the raw frame type of the frame data.
+pub fn frame_type(data: &FrameData) -> u8 {
+    match *data {
+        FrameData::NetMsg((NetMsgFrameType::Start, _)) => 0,
+        FrameData::NetMsg((NetMsgFrameType::Normal, _)) => 1,
+        FrameData::NetMsg((NetMsgFrameType::Unknown(x), _)) => x,
+        FrameData::DemoStart => 2,
+        FrameData::ConsoleCommand(_) => 3,
+        FrameData::ClientData(_) => 4,
+        FrameData::NextSection => 5,
+        FrameData::Event(_) => 6,
+        FrameData::WeaponAnim(_) => 7,
+        FrameData::Sound(_) => 8,
+        FrameData::DemoBuffer(_) => 9,
+    }
+}
+
+fn ref_params<W: Write>(w: &mut W, p: &RefParams) -> Result<()> {
+    xyz(w, &p.vieworg)?;
+    xyz(w, &p.viewangles)?;
+    xyz(w, &p.forward)?;
+    xyz(w, &p.right)?;
+    xyz(w, &p.up)?;
+    f32(w, p.frametime)?;
+    f32(w, p.time)?;
+    i32(w, p.intermission)?;
+    i32(w, p.paused)?;
+    i32(w, p.spectator)?;
+    i32(w, p.onground)?;
+    i32(w, p.waterlevel)?;
+    xyz(w, &p.simvel)?;
+    xyz(w, &p.simorg)?;
+    xyz(w, &p.viewheight)?;
+    f32(w, p.idealpitch)?;
+    xyz(w, &p.cl_viewangles)?;
+    i32(w, p.health)?;
+    xyz(w, &p.crosshairangle)?;
+    f32(w, p.viewsize)?;
+    xyz(w, &p.punchangle)?;
+    i32(w, p.maxclients)?;
+    i32(w, p.viewentity)?;
+    i32(w, p.playernum)?;
+    i32(w, p.max_entities)?;
+    i32(w, p.demoplayback)?;
+    i32(w, p.hardware)?;
+    i32(w, p.smoothing)?;
+    i32(w, p.ptr_cmd)?;
+    i32(w, p.ptr_movevars)?;
+    i32_4(w, &p.viewport)?;
+    i32(w, p.next_view)?;
+    i32(w, p.only_client_draw)
+}
+
+fn usercmd<W: Write>(w: &mut W, c: &UserCmd) -> Result<()> {
+    i16(w, c.lerp_msec)?;
+    u8(w, c.msec)?;
+    padding(w, 1)?;
+    xyz(w, &c.viewangles)?;
+    f32(w, c.forwardmove)?;
+    f32(w, c.sidemove)?;
+    f32(w, c.upmove)?;
+    i8(w, c.lightlevel)?;
+    padding(w, 1)?;
+    u16(w, c.buttons)?;
+    i8(w, c.impulse)?;
+    i8(w, c.weaponselect)?;
+    padding(w, 2)?;
+    i32(w, c.impact_index)?;
+    xyz(w, &c.impact_position)
+}
+
+fn movevars<W: Write>(w: &mut W, m: &MoveVars) -> Result<()> {
+    f32(w, m.gravity)?;
+    f32(w, m.stopspeed)?;
+    f32(w, m.maxspeed)?;
+    f32(w, m.spectatormaxspeed)?;
+    f32(w, m.accelerate)?;
+    f32(w, m.airaccelerate)?;
+    f32(w, m.wateraccelerate)?;
+    f32(w, m.friction)?;
+    f32(w, m.edgefriction)?;
+    f32(w, m.waterfriction)?;
+    f32(w, m.entgravity)?;
+    f32(w, m.bounce)?;
+    f32(w, m.stepsize)?;
+    f32(w, m.maxvelocity)?;
+    f32(w, m.zmax)?;
+    f32(w, m.wave_height)?;
+    i32(w, m.footsteps)?;
+    fixed(w, "sky_name", &m.sky_name, 32)?;
+    f32(w, m.rollangle)?;
+    f32(w, m.rollspeed)?;
+    f32(w, m.skycolor_r)?;
+    f32(w, m.skycolor_g)?;
+    f32(w, m.skycolor_b)?;
+    f32(w, m.skyvec_x)?;
+    f32(w, m.skyvec_y)?;
+    f32(w, m.skyvec_z)
+}
+
+fn net_msg_info<W: Write>(w: &mut W, info: &NetMsgInfo) -> Result<()> {
+    f32(w, info.timestamp)?;
+    ref_params(w, &info.ref_params)?;
+    usercmd(w, &info.usercmd)?;
+    movevars(w, &info.movevars)?;
+    xyz(w, &info.view)?;
+    i32(w, info.viewmodel)
+}
+
+/// Writes NetMsg frame data.
+pub fn net_msg_data<W: Write>(w: &mut W, data: &NetMsgData) -> Result<()> {
+    if data.msg.len() > MAX_MESSAGE_LENGTH as usize {
+        return Err(parse::Error::InvalidNetMsgLength(data.msg.len() as i32).into());
+    }
+
+    net_msg_info(w, &data.info)?;
+    i32(w, data.incoming_sequence)?;
+    i32(w, data.incoming_acknowledged)?;
+    i32(w, data.incoming_reliable_acknowledged)?;
+    i32(w, data.incoming_reliable_sequence)?;
+    i32(w, data.outgoing_sequence)?;
+    i32(w, data.reliable_sequence)?;
+    i32(w, data.last_reliable_sequence)?;
+    length_bytes(w, &data.msg)
+}
+
+fn event_args<W: Write>(w: &mut W, args: &EventArgs) -> Result<()> {
+    i32(w, args.flags)?;
+    i32(w, args.entity_index)?;
+    xyz(w, &args.origin)?;
+    xyz(w, &args.angles)?;
+    xyz(w, &args.velocity)?;
+    i32(w, args.ducking)?;
+    f32(w, args.fparam1)?;
+    f32(w, args.fparam2)?;
+    i32(w, args.iparam1)?;
+    i32(w, args.iparam2)?;
+    i32(w, args.bparam1)?;
+    i32(w, args.bparam2)
+}
+
+/// Writes frame data, without the frame header.
+pub fn frame_data<W: Write>(w: &mut W, data: &FrameData) -> Result<()> {
+    match *data {
+        FrameData::NetMsg((_, ref data)) => net_msg_data(w, data),
+        FrameData::DemoStart | FrameData::NextSection => Ok(()),
+        FrameData::ConsoleCommand(ref data) => fixed(w, "command", &data.command, 64),
+        FrameData::ClientData(ref data) => {
+            xyz(w, &data.origin)?;
+            xyz(w, &data.viewangles)?;
+            i32(w, data.weapon_bits)?;
+            f32(w, data.fov)
+        }
+        FrameData::Event(ref data) => {
+            i32(w, data.flags)?;
+            i32(w, data.index)?;
+            f32(w, data.delay)?;
+            event_args(w, &data.args)
+        }
+        FrameData::WeaponAnim(ref data) => {
+            i32(w, data.anim)?;
+            i32(w, data.body)
+        }
+        FrameData::Sound(ref data) => {
+            i32(w, data.channel)?;
+            length_bytes(w, &data.sample)?;
+            f32(w, data.attenuation)?;
+            f32(w, data.volume)?;
+            i32(w, data.flags)?;
+            i32(w, data.pitch)
+        }
+        FrameData::DemoBuffer(ref data) => length_bytes(w, &data.buffer),
+    }
+}
+
+/// Writes a frame.
+pub fn frame<W: Write>(w: &mut W, frame: &Frame) -> Result<()> {
+    u8(w, frame_type(&frame.data))?;
+    f32(w, frame.time)?;
+    i32(w, frame.frame)?;
+    frame_data(w, &frame.data)
+}
+
+/// Writes frames one after another.
+pub fn frames<W: Write>(w: &mut W, frames: &[Frame]) -> Result<()> {
+    for f in frames {
+        frame(w, f)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,18 @@
+//! Demo header writing functions.
+
+use std::io::Write;
+
+use super::*;
+use parse::header::MAGIC;
+use types::*;
+
+/// Writes a demo header, using the given directory offset.
+pub fn header<W: Write>(w: &mut W, header: &Header, directory_offset: i32) -> Result<()> {
+    w.write_all(MAGIC)?;
+    i32(w, header.demo_protocol)?;
+    i32(w, header.net_protocol)?;
+    fixed(w, "map_name", &header.map_name, 260)?;
+    fixed(w, "game_dir", &header.game_dir, 260)?;
+    u32(w, header.map_crc)?;
+    i32(w, directory_offset)
+}
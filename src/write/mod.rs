@@ -0,0 +1,108 @@
+//! Functions for writing demos back into the demo file format.
+//!
+//! The writers mirror the parsing functions in the `parse` module. Values which describe the file
+//! layout (`Header::directory_offset`, `DirectoryEntry::offset` and
+//! `DirectoryEntry::file_length`) are computed from the data being written rather than taken from
+//! the structs, so the output is always consistent.
+
+use std::io::Write;
+
+use errors::*;
+
+mod demo;
+pub mod directory;
+pub mod frame;
+pub mod header;
+
+pub use self::demo::demo;
+
+#[inline]
+fn u8<W: Write>(w: &mut W, value: u8) -> Result<()> {
+    w.write_all(&[value])?;
+    Ok(())
+}
+
+#[inline]
+fn i8<W: Write>(w: &mut W, value: i8) -> Result<()> {
+    u8(w, value as u8)
+}
+
+#[inline]
+fn i16<W: Write>(w: &mut W, value: i16) -> Result<()> {
+    w.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+#[inline]
+fn u16<W: Write>(w: &mut W, value: u16) -> Result<()> {
+    w.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+#[inline]
+fn i32<W: Write>(w: &mut W, value: i32) -> Result<()> {
+    w.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+#[inline]
+fn u32<W: Write>(w: &mut W, value: u32) -> Result<()> {
+    w.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+#[inline]
+fn f32<W: Write>(w: &mut W, value: f32) -> Result<()> {
+    w.write_all(&value.to_bits().to_le_bytes())?;
+    Ok(())
+}
+
+#[inline]
+fn xyz<W: Write>(w: &mut W, value: &[f32; 3]) -> Result<()> {
+    for &x in value {
+        f32(w, x)?;
+    }
+    Ok(())
+}
+
+#[inline]
+fn i32_4<W: Write>(w: &mut W, value: &[i32; 4]) -> Result<()> {
+    for &x in value {
+        i32(w, x)?;
+    }
+    Ok(())
+}
+
+#[inline]
+fn padding<W: Write>(w: &mut W, count: usize) -> Result<()> {
+    const ZEROS: [u8; 64] = [0; 64];
+
+    let mut left = count;
+    while left > 0 {
+        let chunk = left.min(ZEROS.len());
+        w.write_all(&ZEROS[..chunk])?;
+        left -= chunk;
+    }
+    Ok(())
+}
+
+/// Writes a fixed-size byte field, padding it with zeros.
+///
+/// Returns an error if the value doesn't fit into the field.
+#[inline]
+fn fixed<W: Write>(w: &mut W, field: &'static str, value: &[u8], size: usize) -> Result<()> {
+    if value.len() > size {
+        bail!(ErrorKind::FieldTooLong(field, value.len(), size));
+    }
+
+    w.write_all(value)?;
+    padding(w, size - value.len())
+}
+
+/// Writes a byte buffer prefixed with its length.
+#[inline]
+fn length_bytes<W: Write>(w: &mut W, value: &[u8]) -> Result<()> {
+    i32(w, value.len() as i32)?;
+    w.write_all(value)?;
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Tests of the `hldemo` command-line tool.
+
+extern crate hldemo;
+extern crate serde_json;
+
+use std::fs;
+use std::process::{Command, Output};
+
+use hldemo::Demo;
+
+/// Runs `hldemo` with the given arguments.
+fn hldemo(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hldemo")).args(args)
+                                              .env("RUST_BACKTRACE", "0")
+                                              .output()
+                                              .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Returns the path of the test demo.
+fn test_demo(name: &str) -> String {
+    format!("{}/test-demos/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// Returns a path for an output file.
+fn output_path(name: &str) -> String {
+    format!("{}/{}", env!("CARGO_TARGET_TMPDIR"), name)
+}
+
+/// Returns the total track time of the playback entries.
+fn playback_time(demo: &Demo) -> f32 {
+    demo.directory
+        .entries
+        .iter()
+        .filter(|e| e.entry_type != hldemo::ENTRY_TYPE_LOADING)
+        .map(|e| e.track_time)
+        .sum()
+}
+
+#[test]
+fn info() {
+    let output = hldemo(&["info", &test_demo("basic.dem")]);
+    assert!(output.status.success());
+
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Map name: c1a0\n"));
+    assert!(stdout.contains("Game directory: valve\n"));
+    assert!(stdout.contains("Frame count: 289\n"));
+}
+
+#[test]
+fn probe() {
+    let output = hldemo(&["probe", &test_demo("basic.dem")]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Dialect: GoldSrc\n"));
+    assert!(stdout(&output).contains("Truncated: no\n"));
+
+    // Probing never fails, even on invalid demos.
+    let output = hldemo(&["probe", &test_demo("invalid-magic.dem")]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Dialect: Unknown\n"));
+}
+
+#[test]
+fn frames() {
+    let output = hldemo(&["frames",
+                          &test_demo("basic.dem"),
+                          "--entry",
+                          "1",
+                          "--type",
+                          "ConsoleCommand"]);
+    assert!(output.status.success());
+
+    let stdout = stdout(&output);
+    assert!(!stdout.contains("Entry 0:"));
+    assert!(stdout.contains("command=`-showscores`"));
+    assert!(stdout.lines()
+                  .filter(|line| line.starts_with('\t'))
+                  .all(|line| line.contains("type=ConsoleCommand")));
+}
+
+#[test]
+fn export() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+    let frame_count = demo.directory.entries.iter().map(|e| e.frames.len()).sum::<usize>();
+
+    let path = output_path("export.csv");
+    let output = hldemo(&["export", &test_demo("basic.dem"), "--format", "csv", "-o", &path]);
+    assert!(output.status.success());
+    let csv = fs::read_to_string(&path).unwrap();
+    assert!(csv.starts_with("entry,frame,time,type,"));
+    assert_eq!(csv.lines().count(), frame_count + 1);
+
+    let output = hldemo(&["export", &test_demo("basic.dem"), "--format", "json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["header"]["map_name"], "c1a0");
+
+    let output = hldemo(&["export", &test_demo("basic.dem"), "--format", "hltas"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("version 1\nframes\n"));
+}
+
+#[test]
+fn trim() {
+    let path = output_path("trim.dem");
+    let output = hldemo(&["trim", &test_demo("basic.dem"), &path, "--from", "1", "--to", "2"]);
+    assert!(output.status.success());
+
+    let bytes = fs::read(&path).unwrap();
+    let clip = Demo::parse(&bytes).unwrap();
+    let time = playback_time(&clip);
+    assert!(time > 0.9 && time < 1.1);
+
+    let output = hldemo(&["trim", &test_demo("basic.dem"), &path, "--from", "100"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn concat() {
+    let bytes = include_bytes!("../test-demos/basic.dem");
+    let demo = Demo::parse(bytes).unwrap();
+
+    let path = output_path("concat.dem");
+    let output = hldemo(&["concat",
+                          &test_demo("basic.dem"),
+                          &test_demo("basic.dem"),
+                          "-o",
+                          &path]);
+    assert!(output.status.success());
+
+    let bytes = fs::read(&path).unwrap();
+    let merged = Demo::parse(&bytes).unwrap();
+    assert!((playback_time(&merged) - 2f32 * playback_time(&demo)).abs() < 0.001);
+}
+
+#[test]
+fn validate() {
+    let output = hldemo(&["validate", &test_demo("basic.dem")]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "OK\n");
+
+    let output = hldemo(&["validate", &test_demo("invalid-frame-type.dem")]);
+    assert!(!output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("error: "));
+}
+
+#[test]
+fn validate_warnings() {
+    // Change the frame count of the playback entry in the directory.
+    let mut bytes = include_bytes!("../test-demos/basic.dem").to_vec();
+    let demo = Demo::parse_without_frames(&bytes).unwrap();
+    let frame_count_offset = demo.header.directory_offset as usize + 4 + 92 + 80;
+    let frame_count = demo.directory.entries[1].frame_count + 1;
+    bytes[frame_count_offset..frame_count_offset + 4].copy_from_slice(&frame_count.to_le_bytes());
+
+    let path = output_path("validate-warnings.dem");
+    fs::write(&path, &bytes).unwrap();
+
+    let output = hldemo(&["validate", &path]);
+    assert!(!output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("warning: entry 1 frame count is 290"));
+}